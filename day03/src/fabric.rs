@@ -0,0 +1,230 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{Claim, Result};
+
+/// The fabric is grown to fit the claims, but only up to this many inches
+/// on each side (the puzzle's is 1000).
+const MAX_SIZE: u32 = 2048;
+
+/// A sheet of fabric that claims can be added to and withdrawn from.
+///
+/// The number of overlapping square inches and the set of intact claims are
+/// kept up to date as claims come and go, so each `add_claim`/`remove_claim`
+/// only costs time proportional to that claim's area.
+#[derive(Debug, Default)]
+pub struct Fabric {
+    // note: indexed as [x][y], same as `count_map`; grown on demand
+    cells: Vec<Vec<Cell>>,
+    claims: HashMap<u32, Claim>,
+    // number of cells in each claim that are shared with another claim
+    shared: HashMap<u32, u32>,
+    intact: BTreeSet<u32>,
+    overlapping: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Cell {
+    count: u32,
+    // sum of the ids of every claim covering this cell; when `count` is 1
+    // this is exactly the id of the sole owner
+    id_sum: u64,
+}
+
+impl Fabric {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of square inches covered by two or more claims.
+    pub fn overlapping(&self) -> u32 {
+        self.overlapping
+    }
+
+    /// Ids of the claims that don't overlap any other claim.
+    pub fn intact(&self) -> impl Iterator<Item = u32> + '_ {
+        self.intact.iter().copied()
+    }
+
+    pub fn num_claims(&self) -> usize {
+        self.claims.len()
+    }
+
+    pub fn add_claim(&mut self, claim: Claim) -> Result<()> {
+        if self.claims.contains_key(&claim.id) {
+            return Err(format!("Claim #{} is already on the fabric", claim.id).into());
+        }
+
+        let right = claim.x.checked_add(claim.w).filter(|&x| x <= MAX_SIZE);
+        let bottom = claim.y.checked_add(claim.h).filter(|&y| y <= MAX_SIZE);
+        let (Some(right), Some(bottom)) = (right, bottom) else {
+            return Err(format!("Claim #{} doesn't fit on the fabric (at most {}x{})", claim.id, MAX_SIZE, MAX_SIZE).into());
+        };
+
+        let id = claim.id;
+        self.grow(right, bottom);
+        self.shared.insert(id, 0);
+
+        let mut shared = 0u32;
+        for x in claim.x..right {
+            for y in claim.y..bottom {
+                let cell = &mut self.cells[x as usize][y as usize];
+                match cell.count {
+                    0 => {},
+                    1 => {
+                        // the previous owner now shares this cell with us
+                        let owner = cell.id_sum as u32;
+                        self.overlapping += 1;
+                        shared += 1;
+                        Self::bump_shared(&mut self.shared, &mut self.intact, owner, 1);
+                    },
+                    _ => {
+                        shared += 1;
+                    },
+                }
+                cell.count += 1;
+                cell.id_sum += id as u64;
+            }
+        }
+
+        Self::bump_shared(&mut self.shared, &mut self.intact, id, shared as i64);
+        self.claims.insert(id, claim);
+
+        Ok(())
+    }
+
+    pub fn remove_claim(&mut self, id: u32) -> Result<Claim> {
+        let claim = self.claims
+            .remove(&id)
+            .ok_or_else(|| format!("Claim #{} is not on the fabric", id))?;
+
+        for x in claim.x..(claim.x+claim.w) {
+            for y in claim.y..(claim.y+claim.h) {
+                let cell = &mut self.cells[x as usize][y as usize];
+                cell.count -= 1;
+                cell.id_sum -= id as u64;
+                if cell.count == 1 {
+                    // whoever is left now has this cell to themselves
+                    let owner = cell.id_sum as u32;
+                    self.overlapping -= 1;
+                    Self::bump_shared(&mut self.shared, &mut self.intact, owner, -1);
+                }
+            }
+        }
+
+        self.shared.remove(&id);
+        self.intact.remove(&id);
+
+        Ok(claim)
+    }
+
+    fn bump_shared(shared: &mut HashMap<u32, u32>, intact: &mut BTreeSet<u32>, id: u32, delta: i64) {
+        let entry = shared.entry(id).or_insert(0);
+        *entry = (*entry as i64 + delta) as u32;
+        if *entry == 0 {
+            intact.insert(id);
+        }
+        else {
+            intact.remove(&id);
+        }
+    }
+
+    fn grow(&mut self, w: u32, h: u32) {
+        // every column is kept the same height, so we only need to touch
+        // the existing columns when the fabric gets taller
+        let cur_h = self.cells.first().map(|x| x.len()).unwrap_or(0);
+        let (w, h) = (w as usize, (h as usize).max(cur_h));
+        if h > cur_h {
+            for column in self.cells.iter_mut() {
+                column.resize(h, Cell::default());
+            }
+        }
+        if self.cells.len() < w {
+            self.cells.resize(w, vec![Cell::default(); h]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{part1, part2};
+
+    fn claim(id: u32, x: u32, y: u32, w: u32, h: u32) -> Claim {
+        Claim { id, x, y, w, h }
+    }
+
+    // rebuild the answers from scratch, the same way as parts 1 and 2
+    fn assert_matches_rebuild(fabric: &Fabric, claims: &[Claim]) {
+        let (overlapping, count_map) = part1(claims).unwrap();
+        assert_eq!(fabric.overlapping(), overlapping);
+
+        let mut intact = claims
+            .iter()
+            .filter(|x| part2(std::slice::from_ref(*x), &count_map).is_ok())
+            .map(|x| x.id)
+            .collect::<Vec<_>>();
+        intact.sort_unstable();
+        assert_eq!(fabric.intact().collect::<Vec<_>>(), intact);
+        assert_eq!(fabric.num_claims(), claims.len());
+    }
+
+    #[test]
+    fn random_adds_and_removes() {
+        // tiny xorshift PRNG, so the test is repeatable
+        let mut state = 0x2018_0003u64;
+        let mut next = move |n: u32| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as u32
+        };
+
+        let mut fabric = Fabric::new();
+        let mut claims: Vec<Claim> = Vec::new();
+        for _ in 0..300 {
+            if !claims.is_empty() && next(3) == 0 {
+                let removed = claims.swap_remove(next(claims.len() as u32) as usize);
+                assert_eq!(fabric.remove_claim(removed.id).unwrap().id, removed.id);
+            }
+            else {
+                // widely spread ids, so that the sole owner sums get big
+                let id = 1 + next(u32::MAX - 1);
+                if claims.iter().any(|x| x.id == id) {
+                    continue
+                }
+                let new = claim(id, next(60), next(60), 1 + next(15), 1 + next(15));
+                fabric.add_claim(claim(new.id, new.x, new.y, new.w, new.h)).unwrap();
+                claims.push(new);
+            }
+
+            assert_matches_rebuild(&fabric, &claims);
+        }
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+        let mut fabric = Fabric::new();
+        fabric.add_claim(claim(1, 0, 0, 4, 4)).unwrap();
+        let err = fabric.add_claim(claim(1, 10, 10, 2, 2)).unwrap_err();
+        assert_eq!(err.to_string(), "Claim #1 is already on the fabric");
+        assert_matches_rebuild(&fabric, &[claim(1, 0, 0, 4, 4)]);
+
+        let err = fabric.remove_claim(2).unwrap_err();
+        assert_eq!(err.to_string(), "Claim #2 is not on the fabric");
+    }
+
+    #[test]
+    fn oversized_claims_are_rejected() {
+        let mut fabric = Fabric::new();
+        fabric.add_claim(claim(1, 0, 0, 4, 4)).unwrap();
+        fabric.add_claim(claim(2, 2048 - 3, 0, 3, 2048)).unwrap();
+        for (id, x, y, w, h) in [(3, 2046, 0, 3, 1), (4, 0, 2000, 1, 49), (5, u32::MAX, 0, 1, 1), (6, 0, 1, 1, u32::MAX)] {
+            let err = fabric.add_claim(claim(id, x, y, w, h)).unwrap_err();
+            assert_eq!(err.to_string(), format!("Claim #{} doesn't fit on the fabric (at most 2048x2048)", id));
+        }
+
+        assert_eq!(fabric.num_claims(), 2);
+        assert_eq!(fabric.overlapping(), 0);
+        assert_eq!(fabric.intact().collect::<Vec<_>>(), [1, 2]);
+    }
+}
@@ -3,37 +3,144 @@ use std::fs;
 use std::result;
 use std::str::FromStr;
 use std::io;
+use std::env;
 
+mod fabric;
+mod parse;
+mod render;
 
 const INPUT_FILE: &str = "./input/claims.txt";
 
 type Result<T> = result::Result<T, Box<dyn error::Error>>;
 
 
+#[derive(Debug, Default)]
+struct Options {
+    mode: parse::Mode,
+    render: Option<String>,
+    outline: Option<u32>,
+    interactive: bool,
+}
+
 fn main() -> Result<()> {
-    let buf = io::BufReader::new(fs::File::open(&INPUT_FILE)?);
+    let opts = parse_args(env::args().skip(1))?;
+    let buf = io::BufReader::new(fs::File::open(INPUT_FILE)?);
 
-    let claims = parse_claims(buf)?;
+    let claims = parse_claims(buf, opts.mode)?;
 
     let (p1, count_map) = part1(&claims)?;
     let p2 = part2(&claims, &count_map)?;
 
     println!("Overlapping Count: {}", p1);
-    println!("Best Claim: {}", p2.id);
+    println!("Best Claim: #{}", p2.id);
+
+    if let Some(path) = &opts.render {
+        let outline = match &opts.outline {
+            Some(id) => Some(find_claim(&claims, *id)?),
+            None => None,
+        };
+
+        let out = io::BufWriter::new(fs::File::create(path)?);
+        render::write_heatmap(out, &count_map, outline, Some(p2))?;
+        println!("Heatmap written to: {}", path);
+    }
+
+    if opts.interactive {
+        let stdin = io::stdin();
+        interactive(claims, stdin.lock(), opts.mode)?;
+    }
 
     Ok(())
 }
 
-fn parse_claims<R: io::BufRead>(buf: R) -> Result<Vec<Claim>> {
-    let claims = buf.lines()
-        .flatten()
-        .map(|x| x.parse::<Claim>())
-        .collect::<Result<Vec<Claim>>>();
+fn interactive<R: io::BufRead>(claims: Vec<Claim>, buf: R, mode: parse::Mode) -> Result<()> {
+    // start from the claims in the input file, then apply one command per
+    // line: `add <claim>`, `remove <id>` or `status`
+    let mut fabric = fabric::Fabric::new();
+    for claim in claims {
+        fabric.add_claim(claim)?;
+    }
+
+    for line in buf.lines() {
+        let line = line?;
+        let line = line.trim();
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let res = match command {
+            "add" => parse::parse_claim(arg, mode)
+                .map_err(|e| e.into())
+                .and_then(|claim| fabric.add_claim(claim)),
+            "remove" => arg.trim().trim_start_matches('#')
+                .parse()
+                .map_err(|e: std::num::ParseIntError| e.into())
+                .and_then(|id| fabric.remove_claim(id))
+                .map(|_| ()),
+            "status" | "" => Ok(()),
+            _ => Err(format!("Unknown command: {}", command).into()),
+        };
+
+        if let Err(e) = res {
+            println!("Error: {}", e);
+            continue
+        }
 
+        let intact = fabric.intact()
+            .map(|x| format!("#{}", x))
+            .collect::<Vec<_>>();
+        println!("Claims: {}", fabric.num_claims());
+        println!("Overlapping Count: {}", fabric.overlapping());
+        println!("Intact Claims: {}", intact.join(" "));
+    }
+
+    Ok(())
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options> {
+    let mut opts = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--render" => {
+                opts.render = Some(args.next().ok_or("--render requires an output path")?);
+            },
+            "--outline" => {
+                // allow the id to be given either with or without the leading '#'
+                let id = args.next().ok_or("--outline requires a claim id")?;
+                opts.outline = Some(id.trim_start_matches('#').parse()?);
+            },
+            "--interactive" => {
+                opts.interactive = true;
+            },
+            "--lenient" => {
+                opts.mode = parse::Mode::Lenient;
+            },
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
+
+    if opts.outline.is_some() && opts.render.is_none() {
+        return Err("--outline can only be used together with --render".into());
+    }
+
+    Ok(opts)
+}
+
+fn find_claim(claims: &[Claim], id: u32) -> Result<&Claim> {
     claims
+        .iter()
+        .find(|x| x.id == id)
+        .ok_or_else(|| format!("Could not find claim: #{}", id).into())
+}
+
+fn parse_claims<R: io::BufRead>(buf: R, mode: parse::Mode) -> Result<Vec<Claim>> {
+    buf.lines()
+        .enumerate()
+        .map(|(i, x)| {
+            parse::parse_claim(&x?, mode)
+                .map_err(|e| e.with_line(i + 1).into())
+        })
+        .collect::<Result<Vec<Claim>>>()
 }
 
-fn part1(claims: &Vec<Claim>) -> Result<(u32, Vec<Vec<u8>>)> {
+fn part1(claims: &[Claim]) -> Result<(u32, Vec<Vec<u8>>)> {
     let mut count_map = vec![vec![0u8; 1000]; 1000];
     for claim in claims {
         for x in claim.x..(claim.x+claim.w) {
@@ -58,7 +165,7 @@ fn part1(claims: &Vec<Claim>) -> Result<(u32, Vec<Vec<u8>>)> {
     Ok((count_overlapping, count_map))
 }
 
-fn part2<'a, 'b>(claims: &'a Vec<Claim>, count_map: &'b Vec<Vec<u8>>) -> Result<&'a Claim> {
+fn part2<'a>(claims: &'a [Claim], count_map: &[Vec<u8>]) -> Result<&'a Claim> {
     'outer:
     for claim in claims {
         for x in claim.x..(claim.x+claim.w) {
@@ -82,7 +189,7 @@ fn part2<'a, 'b>(claims: &'a Vec<Claim>, count_map: &'b Vec<Vec<u8>>) -> Result<
 
 #[derive(Debug)]
 struct Claim {
-    id: String,
+    id: u32,
     x: u32,
     y: u32,
     w: u32,
@@ -90,32 +197,9 @@ struct Claim {
 }
 
 impl FromStr for Claim {
-    type Err = Box<dyn error::Error>;
-
-    fn from_str(s: &str) -> std::result::Result<Self, Box<dyn error::Error>> {
-        fn parse<T>(part: Option<&str>, err: &'static str) -> Result<T>
-        where T: std::str::FromStr, <T as FromStr>::Err: std::error::Error + 'static {
-            Ok(part
-                .ok_or(err)?
-                .trim()
-                .parse()?
-            )
-        }
-        
-        // treat these symbols as plain separators; this isn't as strict as
-        // it (possibly) should be, but it does allow some level of inaccuracy
-        // in the input data (e.g. spaces are stripped)
-        let mut parts = s.split(&['@', ',', ':', 'x'][..]);
-        let id = parse(parts.next(), "Could not find box id")?;
-        let x = parse(parts.next(), "Could not find X position")?;
-        let y = parse(parts.next(), "Could not find Y position")?;
-        let w = parse(parts.next(), "Could not find width")?;
-        let h = parse(parts.next(), "Could not find height")?;
-
-        Ok(Self {
-            id,
-            x, y,
-            w, h,
-        })
+    type Err = parse::ParseError;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        parse::parse_claim(s, parse::Mode::Strict)
     }
 }
@@ -0,0 +1,245 @@
+use std::error;
+use std::fmt;
+use std::result;
+
+use crate::Claim;
+
+/// How forgiving the claim parser should be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Only accept `#id @ x,y: wxh` (whitespace between tokens is optional)
+    #[default]
+    Strict,
+    /// Treat `@`, `,`, `:` and `x` as interchangeable separators
+    Lenient,
+}
+
+/// A claim that couldn't be parsed, along with where it went wrong.
+///
+/// Line and column numbers are 1-based; `line` is 0 when the claim was
+/// parsed on its own (e.g. via `str::parse`) rather than from a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub expected: &'static str,
+    pub found: String,
+}
+
+impl ParseError {
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.line = line;
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}, ", self.line)?;
+        }
+        write!(f, "column {}: expected {}, found {}", self.column, self.expected, self.found)
+    }
+}
+
+impl error::Error for ParseError {}
+
+type ParseResult<T> = result::Result<T, ParseError>;
+
+pub fn parse_claim(s: &str, mode: Mode) -> ParseResult<Claim> {
+    match mode {
+        Mode::Strict => parse_strict(s),
+        Mode::Lenient => parse_lenient(s),
+    }
+}
+
+fn parse_strict(s: &str) -> ParseResult<Claim> {
+    let mut scanner = Scanner::new(s);
+
+    scanner.expect('#', "'#'")?;
+    let id = scanner.number("claim id")?;
+    scanner.expect('@', "'@'")?;
+    let x = scanner.number("X position")?;
+    scanner.expect(',', "','")?;
+    let y = scanner.number("Y position")?;
+    scanner.expect(':', "':'")?;
+    let w = scanner.number("width")?;
+    scanner.expect('x', "'x'")?;
+    let h = scanner.number("height")?;
+    scanner.end()?;
+
+    Ok(Claim { id, x, y, w, h })
+}
+
+fn parse_lenient(s: &str) -> ParseResult<Claim> {
+    // treat these symbols as plain separators; this isn't as strict as
+    // it (possibly) should be, but it does allow some level of inaccuracy
+    // in the input data (e.g. spaces are stripped)
+    let mut fields = Vec::with_capacity(5);
+    let mut column = 1;
+    for part in s.split(&['@', ',', ':', 'x'][..]) {
+        fields.push((column, part));
+        column += part.chars().count() + 1;
+    }
+
+    let mut fields = fields.into_iter();
+    let mut field = |expected: &'static str, allow_hash: bool| -> ParseResult<u32> {
+        let (column, part) = fields.next().ok_or(ParseError {
+            line: 0,
+            column: s.chars().count() + 1,
+            expected,
+            found: "end of line".to_string(),
+        })?;
+
+        let mut value = part.trim();
+        if allow_hash {
+            value = value.strip_prefix('#').unwrap_or(value);
+        }
+
+        value.parse().map_err(|_| ParseError {
+            line: 0,
+            column,
+            expected,
+            found: format!("{:?}", part),
+        })
+    };
+
+    let id = field("claim id", true)?;
+    let x = field("X position", false)?;
+    let y = field("Y position", false)?;
+    let w = field("width", false)?;
+    let h = field("height", false)?;
+
+    Ok(Claim { id, x, y, w, h })
+}
+
+struct Scanner<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(s: &'a str) -> Self {
+        Self { s, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self, expected: &'static str) -> ParseError {
+        let found = match self.rest().chars().next() {
+            Some(c) => format!("{:?}", c),
+            None => "end of line".to_string(),
+        };
+
+        ParseError {
+            line: 0,
+            column: self.s[..self.pos].chars().count() + 1,
+            expected,
+            found,
+        }
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> ParseResult<()> {
+        self.skip_whitespace();
+        if !self.rest().starts_with(c) {
+            return Err(self.error(expected));
+        }
+        self.pos += c.len_utf8();
+
+        Ok(())
+    }
+
+    fn number(&mut self, expected: &'static str) -> ParseResult<u32> {
+        self.skip_whitespace();
+        let len = self.rest()
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error(expected));
+        }
+
+        let value = self.rest()[..len]
+            .parse()
+            .map_err(|_| ParseError {
+                found: format!("{:?} (too large)", &self.rest()[..len]),
+                ..self.error(expected)
+            })?;
+        self.pos += len;
+
+        Ok(value)
+    }
+
+    fn end(&mut self) -> ParseResult<()> {
+        self.skip_whitespace();
+        if !self.rest().is_empty() {
+            return Err(self.error("end of line"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strict_error(s: &str) -> String {
+        parse_claim(s, Mode::Strict).unwrap_err().to_string()
+    }
+
+    fn fields(claim: Claim) -> (u32, u32, u32, u32, u32) {
+        (claim.id, claim.x, claim.y, claim.w, claim.h)
+    }
+
+    #[test]
+    fn strict_accepts_the_puzzle_format() {
+        assert_eq!(fields(parse_claim("#123 @ 3,2: 5x4", Mode::Strict).unwrap()), (123, 3, 2, 5, 4));
+        assert_eq!(fields(parse_claim("#1@3,2:5x4", Mode::Strict).unwrap()), (1, 3, 2, 5, 4));
+    }
+
+    #[test]
+    fn strict_points_at_the_mistake() {
+        assert_eq!(strict_error("#1 @ 1x2,3:4"), "column 7: expected ',', found 'x'");
+        assert_eq!(strict_error("1 @ 1,2: 3x4"), "column 1: expected '#', found '1'");
+        assert_eq!(strict_error("#1 @ 1,2: 3x"), "column 13: expected height, found end of line");
+    }
+
+    #[test]
+    fn strict_rejects_trailing_garbage() {
+        assert_eq!(strict_error("#1 @ 1,2: 3x4 extra"), "column 15: expected end of line, found 'e'");
+    }
+
+    #[test]
+    fn strict_rejects_numbers_that_are_too_large() {
+        assert_eq!(
+            strict_error("#1 @ 1,99999999999: 3x4"),
+            "column 8: expected Y position, found \"99999999999\" (too large)",
+        );
+    }
+
+    #[test]
+    fn lenient_accepts_loose_separators() {
+        assert_eq!(fields(parse_claim("#7 @ 1,2: 3x4", Mode::Lenient).unwrap()), (7, 1, 2, 3, 4));
+        assert_eq!(fields(parse_claim("7@1,2:3x4", Mode::Lenient).unwrap()), (7, 1, 2, 3, 4));
+        assert_eq!(fields(parse_claim("#1 @ 1x2,3:4", Mode::Lenient).unwrap()), (1, 1, 2, 3, 4));
+
+        let err = parse_claim("#7 @ 1,2: 3", Mode::Lenient).unwrap_err();
+        assert_eq!(err.to_string(), "column 12: expected height, found end of line");
+    }
+
+    #[test]
+    fn parse_claims_adds_the_line_number() {
+        let claims = crate::parse_claims("#1 @ 1,2: 3x4\n#2 @ 5,6: 7x8\n".as_bytes(), Mode::Strict).unwrap();
+        assert_eq!(claims.len(), 2);
+
+        let err = crate::parse_claims("#1 @ 1,2: 3x4\n#2 @ 1,2 3x4\n".as_bytes(), Mode::Strict).unwrap_err();
+        assert_eq!(err.to_string(), "line 2, column 10: expected ':', found '3'");
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::Write;
+
+use crate::{Claim, Result};
+
+type Rgb = [u8; 3];
+
+const COLOUR_EMPTY: Rgb = [0, 0, 0];
+const COLOUR_SINGLE: Rgb = [24, 48, 112];
+const COLOUR_OUTLINE: Rgb = [255, 255, 255];
+const COLOUR_INTACT: Rgb = [0, 255, 64];
+
+/// Write the `count_map` out as a binary PPM (P6) image.
+///
+/// Cells claimed once are drawn in a dark blue, and overlapping cells are
+/// drawn on a red -> yellow ramp, scaled to the deepest overlap in the map.
+/// The `outline` claim (if any) gets a white border, and the `intact` claim
+/// (if any) gets a green border.
+pub fn write_heatmap<W: Write>(
+    mut out: W,
+    count_map: &[Vec<u8>],
+    outline: Option<&Claim>,
+    intact: Option<&Claim>,
+) -> Result<()> {
+    // note: count_map is indexed as [x][y]
+    let w = count_map.len();
+    let h = count_map.first().map(|x| x.len()).unwrap_or(0);
+    let max_depth = count_map
+        .iter()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or(0);
+
+    let mut pixels = vec![COLOUR_EMPTY; w * h];
+    for (x, column) in count_map.iter().enumerate() {
+        for (y, &depth) in column.iter().enumerate() {
+            pixels[x + y * w] = depth_colour(depth, max_depth);
+        }
+    }
+
+    // draw the outlines last, so they sit on top of the heatmap
+    if let Some(claim) = outline {
+        draw_outline(&mut pixels, w, h, claim, COLOUR_OUTLINE);
+    }
+    if let Some(claim) = intact {
+        draw_outline(&mut pixels, w, h, claim, COLOUR_INTACT);
+    }
+
+    write!(out, "P6\n{} {}\n255\n", w, h)?;
+    for pixel in pixels {
+        out.write_all(&pixel)?;
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+fn depth_colour(depth: u8, max_depth: u8) -> Rgb {
+    match depth {
+        0 => COLOUR_EMPTY,
+        1 => COLOUR_SINGLE,
+        _ => {
+            // scale 2..=max_depth into 0..=255; with only a single level of
+            // overlap the whole map is just red
+            let span = (max_depth.max(3) - 2) as u32;
+            let level = ((depth - 2) as u32 * 255 / span) as u8;
+            [255, level, 0]
+        },
+    }
+}
+
+fn draw_outline(pixels: &mut [Rgb], w: usize, h: usize, claim: &Claim, colour: Rgb) {
+    if claim.w == 0 || claim.h == 0 {
+        return
+    }
+
+    let x0 = claim.x as usize;
+    let y0 = claim.y as usize;
+    let x1 = (claim.x + claim.w - 1) as usize;
+    let y1 = (claim.y + claim.h - 1) as usize;
+    let mut set = |x: usize, y: usize| {
+        if x < w && y < h {
+            pixels[x + y * w] = colour;
+        }
+    };
+
+    for x in x0..=x1 {
+        set(x, y0);
+        set(x, y1);
+    }
+    for y in y0..=y1 {
+        set(x0, y);
+        set(x1, y);
+    }
+}
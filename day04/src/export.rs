@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Consciousness, Guard, Result, Shift};
+
+/// File formats the parsed guards can be exported to (and imported from).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+const CSV_HEADER: &str = "guard,date,sleep_start,sleep_end";
+
+// note: these mirror `Guard`/`Shift`, but only keep the sleep intervals
+// (as half-open minutes since midnight of the shift date), which is all
+// that's needed to rebuild them
+#[derive(Debug, Serialize, Deserialize)]
+struct GuardRecord {
+    id: u32,
+    shifts: Vec<ShiftRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ShiftRecord {
+    date: String,
+    sleep: Vec<(i32, i32)>,
+}
+
+pub fn write_guards<W: Write>(mut out: W, guards: &[Guard], format: Format) -> Result<()> {
+    let records = to_records(guards);
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, &records)?;
+            writeln!(out)?;
+        },
+        Format::Csv => {
+            // one row per sleep interval; shifts where the guard never fell
+            // asleep get a single row with the interval left blank
+            writeln!(out, "{}", CSV_HEADER)?;
+            for guard in &records {
+                for shift in &guard.shifts {
+                    if shift.sleep.is_empty() {
+                        writeln!(out, "{},{},,", guard.id, shift.date)?;
+                    }
+                    for (start, end) in &shift.sleep {
+                        writeln!(out, "{},{},{},{}", guard.id, shift.date, start, end)?;
+                    }
+                }
+            }
+        },
+    }
+
+    Ok(())
+}
+
+pub fn read_guards<R: BufRead>(buf: R, format: Format) -> Result<Vec<Guard>> {
+    let records: Vec<GuardRecord> = match format {
+        Format::Json => serde_json::from_reader(buf)?,
+        Format::Csv => read_csv(buf)?,
+    };
+
+    from_records(records)
+}
+
+fn read_csv<R: BufRead>(buf: R) -> Result<Vec<GuardRecord>> {
+    // group the rows by guard, then by shift date (in file order)
+    let mut guards: BTreeMap<u32, Vec<ShiftRecord>> = BTreeMap::new();
+    for (i, line) in buf.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || (i == 0 && line == CSV_HEADER) {
+            continue
+        }
+
+        let fields = line.split(',').map(|x| x.trim()).collect::<Vec<_>>();
+        let [id, date, start, end] = fields[..] else {
+            return Err(format!("Line {}: expected 4 fields ({}), found {}", i + 1, CSV_HEADER, fields.len()).into());
+        };
+
+        let id: u32 = id.parse().map_err(|e| format!("Line {}: invalid guard id: {}", i + 1, e))?;
+        let shifts = guards.entry(id).or_default();
+        if shifts.last().is_none_or(|x| x.date != date) {
+            shifts.push(ShiftRecord { date: date.to_string(), sleep: Vec::new() });
+        }
+
+        match (start, end) {
+            ("", "") => {},
+            _ => {
+                let interval = (
+                    start.parse().map_err(|e| format!("Line {}: invalid sleep_start: {}", i + 1, e))?,
+                    end.parse().map_err(|e| format!("Line {}: invalid sleep_end: {}", i + 1, e))?,
+                );
+                shifts.last_mut().unwrap().sleep.push(interval);
+            },
+        }
+    }
+
+    Ok(guards
+        .into_iter()
+        .map(|(id, shifts)| GuardRecord { id, shifts })
+        .collect())
+}
+
+fn to_records(guards: &[Guard]) -> Vec<GuardRecord> {
+    let mut records = guards
+        .iter()
+        .map(|guard| {
+            let mut shifts = guard.shifts
+                .iter()
+                .map(|shift| ShiftRecord {
+                    date: shift.date.to_string(),
+                    sleep: shift.entries
+                        .iter()
+                        .filter(|x| x.consciousness == Consciousness::Asleep)
+                        .map(|x| (x.time_start, x.time_end))
+                        .collect(),
+                })
+                .collect::<Vec<_>>();
+            shifts.sort_by(|a, b| a.date.cmp(&b.date));
+
+            GuardRecord { id: guard.id, shifts }
+        })
+        .collect::<Vec<_>>();
+    records.sort_by_key(|x| x.id);
+
+    records
+}
+
+fn from_records(records: Vec<GuardRecord>) -> Result<Vec<Guard>> {
+    // note: the same guard may show up more than once (e.g. when files
+    // are concatenated), so merge their shifts
+    let mut guards: BTreeMap<u32, Guard> = BTreeMap::new();
+    for record in records {
+        let guard = guards
+            .entry(record.id)
+            .or_insert_with(|| Guard::new(record.id));
+        for shift_record in record.shifts {
+            let mut shift = Shift::new(shift_record.date.parse()?);
+            let mut sleep = shift_record.sleep;
+            sleep.sort();
+
+            let mut last_end = i32::MIN;
+            for (start, end) in sleep {
+                if start >= end {
+                    return Err(format!("Guard #{} on {}: empty sleep interval {}..{}", record.id, shift.date, start, end).into());
+                }
+                if start < last_end {
+                    return Err(format!("Guard #{} on {}: overlapping sleep interval {}..{}", record.id, shift.date, start, end).into());
+                }
+                last_end = end;
+
+                shift.push_entry(start, end, Consciousness::Asleep);
+            }
+            guard.push_shift(shift);
+        }
+    }
+
+    Ok(guards.into_values().collect())
+}
+
+impl Format {
+    /// Guess the format from a file name's extension.
+    pub fn from_path(path: &str) -> Result<Self> {
+        let ext = path.rsplit_once('.').map(|x| x.1).unwrap_or("");
+        ext.parse()
+    }
+}
+
+impl FromStr for Format {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unknown format (expected csv or json): {}", s).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io;
+
+    use super::*;
+    use crate::{build_guards, check_records, parse_records, part1, part2, OrphanPolicy, Window};
+
+    fn load(path: &str, window: Window) -> Vec<Guard> {
+        let records = parse_records(io::BufReader::new(fs::File::open(path).unwrap())).unwrap();
+        check_records(&records, OrphanPolicy::Error, window).unwrap();
+        build_guards(records)
+    }
+
+    fn write(guards: &[Guard], format: Format) -> String {
+        let mut out = Vec::new();
+        write_guards(&mut out, guards, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn assert_round_trips(path: &str, window: Window) {
+        let guards = load(path, window);
+        for format in [Format::Csv, Format::Json] {
+            let written = write(&guards, format);
+            let read = read_guards(written.as_bytes(), format).unwrap();
+            assert_eq!(part1(&read, window).unwrap(), part1(&guards, window).unwrap(), "{:?}", format);
+            assert_eq!(part2(&read, window).unwrap(), part2(&guards, window).unwrap(), "{:?}", format);
+            // and nothing is lost along the way
+            assert_eq!(write(&read, format), written, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_round_trips("./input/guards.txt", Window::Midnight);
+        assert_round_trips("./input/malformed/unsorted.txt", Window::Midnight);
+        assert_round_trips("./input/malformed/shift_without_events.txt", Window::Midnight);
+    }
+
+    #[test]
+    fn round_trip_overnight() {
+        assert_round_trips("./input/malformed/multi_day.txt", Window::Day);
+    }
+
+    #[test]
+    fn csv_row_without_sleep() {
+        let csv = format!("{}\n10,1518-11-01,,\n10,1518-11-02,5,25\n", CSV_HEADER);
+        let guards = read_guards(csv.as_bytes(), Format::Csv).unwrap();
+        assert_eq!(guards.len(), 1);
+        assert_eq!(guards[0].shifts.len(), 2);
+        assert!(guards[0].shifts[0].entries.is_empty());
+        assert_eq!(guards[0].shifts[1].entries.len(), 1);
+        assert_eq!(write(&guards, Format::Csv), csv);
+    }
+
+    #[test]
+    fn bad_intervals_are_rejected() {
+        let csv = format!("{}\n10,1518-11-01,5,25\n10,1518-11-01,20,30\n", CSV_HEADER);
+        let err = read_guards(csv.as_bytes(), Format::Csv).unwrap_err();
+        assert_eq!(err.to_string(), "Guard #10 on 1518-11-01: overlapping sleep interval 20..30");
+
+        let json = r#"[{"id": 10, "shifts": [{"date": "1518-11-01", "sleep": [[5, 5]]}]}]"#;
+        let err = read_guards(json.as_bytes(), Format::Json).unwrap_err();
+        assert_eq!(err.to_string(), "Guard #10 on 1518-11-01: empty sleep interval 5..5");
+
+        let csv = format!("{}\n10,1518-11-01,30,20\n", CSV_HEADER);
+        let err = read_guards(csv.as_bytes(), Format::Csv).unwrap_err();
+        assert_eq!(err.to_string(), "Guard #10 on 1518-11-01: empty sleep interval 30..20");
+
+        let csv = format!("{}\n10,1518-11-01,5\n", CSV_HEADER);
+        let err = read_guards(csv.as_bytes(), Format::Csv).unwrap_err();
+        assert_eq!(err.to_string(), "Line 2: expected 4 fields (guard,date,sleep_start,sleep_end), found 3");
+    }
+}
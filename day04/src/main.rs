@@ -4,20 +4,101 @@ use std::result;
 use std::io::{self, BufRead};
 use std::collections::HashMap;
 use std::cmp;
+use std::env;
+
+mod export;
+mod record;
+mod render;
+mod stats;
+mod validate;
+
+use record::{Date, Event, Record};
+use validate::Problem;
 
 const INPUT_FILE: &str = "./input/guards.txt";
 
 type Result<T> = result::Result<T, Box<dyn error::Error>>;
 
+/// What to do with sleep/wake lines that appear before the first
+/// "begins shift" line, where we can't know which guard they belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum OrphanPolicy {
+    #[default]
+    Error,
+    Skip,
+}
+
+/// Which part of the day the strategies look at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Window {
+    /// Only 00:00-00:59 on each shift's date (the puzzle's rules)
+    #[default]
+    Midnight,
+    /// Every minute of the day, for shifts spanning arbitrary hours and days
+    Day,
+}
+
+#[derive(Debug)]
+struct Options {
+    input: String,
+    orphans: OrphanPolicy,
+    window: Window,
+    validate_only: bool,
+    grid: bool,
+    filter: render::Filter,
+    top: Option<usize>,
+    metric: stats::Metric,
+    summary: Option<u32>,
+    import: Option<String>,
+    export: Option<export::Format>,
+}
+
 fn main() -> Result<()> {
-    let buf = io::BufReader::new(fs::File::open(&INPUT_FILE)?);
-    let mut guards = parse_guards(buf)?;
+    let opts = parse_args(env::args().skip(1))?;
+    let guards = match &opts.import {
+        Some(path) => {
+            // previously exported (or hand-made) guards skip the log
+            // parsing and validation entirely
+            let buf = io::BufReader::new(fs::File::open(path)?);
+            export::read_guards(buf, export::Format::from_path(path)?)?
+        },
+        None => {
+            let buf = io::BufReader::new(fs::File::open(&opts.input)?);
+            let records = parse_records(buf)?;
+
+            check_records(&records, opts.orphans, opts.window)?;
+            if opts.validate_only {
+                println!("Log OK: {} lines", records.len());
+                return Ok(())
+            }
+
+            build_guards(records)
+        },
+    };
 
-    // pre-sort guards by sleepiness
-    guards.sort_by_cached_key(|x| cmp::Reverse(x.time_asleep()));
+    if let Some(format) = opts.export {
+        let stdout = io::stdout();
+        return export::write_guards(stdout.lock(), &guards, format);
+    }
+    if opts.grid {
+        let stdout = io::stdout();
+        return render::write_grid(stdout.lock(), &guards, &opts.filter);
+    }
+    if let Some(n) = opts.top {
+        let stdout = io::stdout();
+        return stats::write_top(stdout.lock(), &guards, opts.metric, opts.window, n);
+    }
+    if let Some(id) = opts.summary {
+        let guard = guards
+            .iter()
+            .find(|x| x.id == id)
+            .ok_or_else(|| format!("Could not find guard #{}", id))?;
+        let stdout = io::stdout();
+        return stats::write_summary(stdout.lock(), guard, opts.window);
+    }
 
-    let strat1 = part1(&guards)?;
-    let strat2 = part2(&guards)?;
+    let strat1 = part1(&guards, opts.window)?;
+    let strat2 = part2(&guards, opts.window)?;
 
     println!("Strategy #1: {}", strat1);
     println!("Strategy #2: {}", strat2);
@@ -25,161 +106,241 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn parse_guards<T: BufRead>(buf: T) -> Result<Vec<Guard>> {
-    let mut lines = buf
-        .lines()
-        .collect::<std::result::Result<Vec<_>, _>>()?;
-    
-    // sort entries by their date/time (thank you, ISO8601!)
-    lines.sort();
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options> {
+    let mut opts = Options {
+        input: INPUT_FILE.to_string(),
+        orphans: OrphanPolicy::default(),
+        window: Window::default(),
+        validate_only: false,
+        grid: false,
+        filter: render::Filter::default(),
+        top: None,
+        metric: stats::Metric::TotalSleep,
+        summary: None,
+        import: None,
+        export: None,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => {
+                opts.input = args.next().ok_or("--input requires a path")?;
+            },
+            "--orphans" => {
+                opts.orphans = match args.next().as_deref() {
+                    Some("error") => OrphanPolicy::Error,
+                    Some("skip") => OrphanPolicy::Skip,
+                    _ => return Err("--orphans should be one of: error, skip".into()),
+                };
+            },
+            "--window" => {
+                opts.window = match args.next().as_deref() {
+                    Some("midnight") => Window::Midnight,
+                    Some("day") => Window::Day,
+                    _ => return Err("--window should be one of: midnight, day".into()),
+                };
+            },
+            "--validate" => {
+                opts.validate_only = true;
+            },
+            "--grid" => {
+                opts.grid = true;
+            },
+            "--guard" => {
+                let id = args.next().ok_or("--guard requires a guard id")?;
+                opts.filter.guard = Some(id.trim_start_matches('#').parse()?);
+            },
+            "--from" => {
+                opts.filter.from = Some(args.next().ok_or("--from requires a date")?.parse()?);
+            },
+            "--to" => {
+                opts.filter.to = Some(args.next().ok_or("--to requires a date")?.parse()?);
+            },
+            "--top" => {
+                opts.top = Some(args.next().ok_or("--top requires a number of guards")?.parse()?);
+            },
+            "--by" => {
+                opts.metric = args.next().ok_or("--by requires a metric")?.parse()?;
+            },
+            "--import" => {
+                opts.import = Some(args.next().ok_or("--import requires a path")?);
+            },
+            "--export" => {
+                opts.export = Some(args.next().ok_or("--export requires a format")?.parse()?);
+            },
+            "--summary" => {
+                let id = args.next().ok_or("--summary requires a guard id")?;
+                opts.summary = Some(id.trim_start_matches('#').parse()?);
+            },
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
 
+    Ok(opts)
+}
 
-    let mut lines_iter = lines.into_iter().peekable();
-    let mut guards: HashMap<u32, Guard> = HashMap::new();
-    while let Some(guard_line) = lines_iter.peek() {
-        if !guard_line.contains("#") {
-            // if the first few lines in a file are all time entries
-            // (where we do not know the guard ID), then skip them
+fn parse_records<T: BufRead>(buf: T) -> Result<Vec<Record>> {
+    let mut records = buf
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let mut record: Record = line?
+                .parse()
+                .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+            record.line_no = i + 1;
+            Ok(record)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // sort entries by their date/time; the sort is stable, so any entries
+    // with identical timestamps stay in file order
+    records.sort_by_key(|x| x.timestamp);
+
+    Ok(records)
+}
+
+fn check_records(records: &[Record], orphans: OrphanPolicy, window: Window) -> Result<()> {
+    let mut num_errors = 0;
+    for issue in validate::validate(records, window == Window::Midnight) {
+        // entries before the first shift are the only problem we know how
+        // to work around (by skipping them), if the policy allows it
+        if issue.problem == Problem::NoShift && orphans == OrphanPolicy::Skip {
+            eprintln!("Warning: skipping {}", issue);
             continue
         }
 
-        // extract the guard ID and date
-        let guard_line = lines_iter.next().unwrap();
-        let guard_id = guard_line
-            .chars()
-            .skip_while(|&x| x != '#')
-            .skip(1) // skip the '#' char 
-            .take_while(|&x| x.is_digit(10))
-            .collect::<String>()
-            .parse::<u32>()?;
-        
-        // note: the guards shift may actually start the day *before*
-        // the entry lines (which are what we really care about),
-        // so we'll attempt a peek. if the next line is actually
-        // another guard shift line, we'll fall back to the current
-        // line
-        let shift_date: String;
-        match lines_iter.peek() {
-            Some(next_line) if !next_line.contains('#') => {
-                shift_date = next_line[1..11].to_string();
-            },
-            _ => {
-                shift_date = guard_line[1..11].to_string();
-            }
-        }
+        eprintln!("Error: {}", issue);
+        num_errors += 1;
+    }
+
+    if num_errors > 0 {
+        return Err(format!("Found {} problem(s) in the guard log", num_errors).into());
+    }
+
+    Ok(())
+}
+
+/// The date whose midnight hour a shift covers.
+fn shift_date(record: &Record) -> Date {
+    // note: the guards shift may actually start the day *before*
+    // the midnight hour (which is what we really care about), so
+    // any shift starting in the afternoon or evening belongs to
+    // the following day
+    match record.timestamp.hour {
+        12.. => record.timestamp.date.succ(),
+        _ => record.timestamp.date,
+    }
+}
+
+/// Build up the guards and their shifts from a sorted, validated log;
+/// any entries before the first shift are dropped.
+fn build_guards(records: Vec<Record>) -> Vec<Guard> {
+    let mut records_iter = records
+        .into_iter()
+        .skip_while(|x| !matches!(x.event, Event::BeginShift(_)))
+        .peekable();
+    let mut guards: HashMap<u32, Guard> = HashMap::new();
+    while let Some(guard_record) = records_iter.next() {
+        let guard_id = match guard_record.event {
+            Event::BeginShift(id) => id,
+            _ => unreachable!(),
+        };
+        let shift_date = shift_date(&guard_record);
 
         // getsert the gaurd
         let guard = guards
             .entry(guard_id)
-            .or_insert(Guard::new(guard_id));
+            .or_insert_with(|| Guard::new(guard_id));
 
         // creat a new shift for this chunk of lines
         let mut shift = Shift::new(shift_date);
-        
-        // loop through the sleep/wake lines
-        let mut last_time = 0u8;
-        let mut last_type = Consciousness::Awake;
-        while let Some(entry_line) = lines_iter.peek() {
-            if entry_line.contains('#') {
-                // if our peek() returns a new line with a guard id,
-                // then we should break this loop
-                break
-            }
-            
-            // extract data from the line
-            let entry_line = lines_iter.next().unwrap();
-            let entry_time = entry_line[15..17].parse()?;
-            let entry_type = if entry_line.contains("falls asleep") {
-                // note: we're actually matching the end of the *last entry*,
-                // so the Asleep/Awake appear to be 'reversed' here
-                Consciousness::Awake
-            }
-            else if entry_line.contains("wakes up") {
-                Consciousness::Asleep
-            }
-            else {
-                return Err(format!("Invalid entry line, should contain 'falls asleep' or 'wakes up': {}", &entry_line).into());
+
+        // loop through the sleep/wake lines; each event closes off the
+        // entry for the state the guard was in up until that point
+        let mut last_time = 0i32;
+        let mut state = Consciousness::Awake;
+        while let Some(entry_record) = records_iter.next_if(|x| !matches!(x.event, Event::BeginShift(_))) {
+            // entry times are in minutes since the midnight that starts
+            // the shift date, so 01:10 is 70, and 23:59 the day before is -1
+            let entry_time = entry_record.timestamp.minutes_since(shift_date);
+            let next_state = match entry_record.event {
+                Event::FallsAsleep => Consciousness::Asleep,
+                Event::WakesUp => Consciousness::Awake,
+                Event::BeginShift(_) => unreachable!(),
             };
 
             // note: we can skip adding the entry if the guard falls asleep
             // immediately (at 00:00)
-            if entry_time > 0 {
-                // build entry and push into list
-                shift.push_entry(
-                    last_time, 
-                    entry_time - 1, 
-                    entry_type, 
-                    &entry_line
-                );
+            if entry_time > last_time {
+                // build entry and push into list; entries are half-open,
+                // so the guard is in `state` up until (not including)
+                // the minute of this event
+                shift.push_entry(last_time, entry_time, state);
             }
 
             // ensure the last_time is updated, so our next run through can
             // correctly assign a 'time_from'
             last_time = entry_time;
-            last_type = entry_type;
+            state = next_state;
         }
 
-        // we need to push one last entry to account for a trailing
-        // sleep/wake event
-        shift.push_entry(
-            last_time,
-            60,
-            match last_type {
-                Consciousness::Awake => Consciousness::Asleep,
-                Consciousness::Asleep => Consciousness::Awake,
-            },
-            "Finalizer line",
-        );
+        // we need to push one last entry to account for whatever state the
+        // guard was left in after the last event, up until the end of the
+        // midnight hour
+        shift.push_entry(last_time, cmp::max(last_time, 60), state);
 
         // finally, add the shift to the guard
         guard.push_shift(shift);
     }
 
-    Ok(guards.into_values().collect())
+    guards.into_values().collect()
 }
 
-fn part1(guards: &Vec<Guard>) -> Result<u32> {
-    // find the sleepiest guard
-    let guard = guards.get(0)
+fn part1(guards: &[Guard], window: Window) -> Result<u32> {
+    // find the sleepiest guard; ties go to the lowest guard id
+    let (guard, minutes) = guards
+        .iter()
+        .map(|x| (x, window.histogram(x)))
+        .max_by_key(|(guard, minutes)| (minutes.iter().sum::<u32>(), cmp::Reverse(guard.id)))
         .ok_or("There are no guards!".to_string())?;
 
-    // grab an array of the total times the guard was asleep
-    // for each minute in the hour
-    let minutes = guard.get_minutes();
+    // grab the minute the guard was most often asleep
+    let (minute, _) = sleepiest_minute(&minutes);
 
-    // sort and collect into a vec of (minute, num_sleeps)
-    let mut minutes: Vec<(usize, u8)> = minutes
-        .into_iter()
-        .enumerate()
-        .collect();
-    minutes.sort_by_cached_key(|x| cmp::Reverse(x.1));
+    Ok(guard.id * minute as u32)
+}
 
-    debug_assert!(minutes.len() > 0);
-    Ok(guard.id * minutes[0].0 as u32)
+fn part2(guards: &[Guard], window: Window) -> Result<u32> {
+    // find the guard who was most often asleep on any one minute; ties go
+    // to the lowest guard id, then the earliest minute
+    let (guard, minute) = guards
+        .iter()
+        .map(|x| (x, sleepiest_minute(&window.histogram(x))))
+        .max_by_key(|(guard, (minute, count))| (*count, cmp::Reverse(guard.id), cmp::Reverse(*minute)))
+        .map(|(guard, (minute, _))| (guard, minute))
+        .ok_or("There are no guards!".to_string())?;
+
+    Ok(guard.id * minute as u32)
 }
 
-fn part2(guards: &Vec<Guard>) -> Result<u32> {
-    let mut sleepiest_guard: (u32, usize, u8) = (0, 0, 0);
-    for guard in guards {
-        let minutes = guard.get_minutes();
-        let max_min = minutes
-            .iter()
-            .enumerate()
-            .fold((0usize, 0u8), |acc, (i, &x)| {
-                if x > acc.1 {
-                    (i, x)
-                }
-                else {
-                    acc
-                }
-            });
-        
-        if max_min.1 > sleepiest_guard.2 {
-            sleepiest_guard = (guard.id, max_min.0, max_min.1);
+/// Find the (minute, count) with the highest count; ties go to the
+/// earliest minute.
+fn sleepiest_minute(minutes: &[u32]) -> (usize, u32) {
+    minutes
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by_key(|&(minute, count)| (count, cmp::Reverse(minute)))
+        .unwrap_or((0, 0))
+}
+
+impl Window {
+    /// How many times the guard was asleep on each minute of the window.
+    fn histogram(&self, guard: &Guard) -> Vec<u32> {
+        match self {
+            Window::Midnight => guard.get_minutes().to_vec(),
+            Window::Day => guard.get_minutes_of_day(),
         }
     }
-
-    Ok(sleepiest_guard.0 * sleepiest_guard.1 as u32)
 }
 
 #[derive(Debug, Clone)]
@@ -190,14 +351,16 @@ struct Guard {
 
 #[derive(Debug, Clone)]
 struct Shift {
-    date: String,
+    date: Date,
     entries: Vec<Entry>
 }
 
 #[derive(Debug, Clone)]
 struct Entry {
-    time_start: u8,
-    time_end: u8,
+    // minutes since midnight of the shift date, as a half-open interval
+    // (time_start..time_end)
+    time_start: i32,
+    time_end: i32,
     consciousness: Consciousness,
 }
 
@@ -219,76 +382,216 @@ impl Guard {
         self.shifts.push(shift);
     }
 
-    fn time_asleep(&self) -> u32 {
-        self.shifts
-            .iter()
-            .map(|x| x.time_asleep())
-            .sum()
-    }
-
     fn get_entries(&self) -> impl Iterator<Item = &Entry> {
         self.shifts
             .iter()
-            .map(|x| &x.entries)
-            .flatten()
+            .flat_map(|x| &x.entries)
     }
 
-    fn get_minutes(&self) -> [u8; 61] {
-        // build an array showing the minutes the guard is asleep
-        // note: array is 61-long, because of :00
-        let mut minutes = [0u8; 61];
+    fn get_minutes(&self) -> [u32; 60] {
+        // build an array showing how many times the guard was asleep on
+        // each minute of the midnight hour
+        let mut minutes = [0u32; 60];
         let asleep_entries = self
             .get_entries()
             .filter(|x| x.consciousness == Consciousness::Asleep);
         for entry in asleep_entries {
-            for i in entry.time_start..=entry.time_end {
-                debug_assert!(i <= 60);
+            // only the midnight hour is interesting here, so clip any
+            // entries that run before or after it
+            let start = cmp::max(entry.time_start, 0);
+            let end = cmp::min(entry.time_end, 60);
+            for i in start..end {
                 minutes[i as usize] += 1;
             }
         }
 
         minutes
     }
+
+    fn get_minutes_of_day(&self) -> Vec<u32> {
+        // same as `get_minutes`, but for every minute of the day; entries
+        // can span any number of hours (or days), and wrap around midnight
+        let mut minutes = vec![0u32; record::MINUTES_PER_DAY as usize];
+        let asleep_entries = self
+            .get_entries()
+            .filter(|x| x.consciousness == Consciousness::Asleep);
+        for entry in asleep_entries {
+            for i in entry.time_start..entry.time_end {
+                minutes[i.rem_euclid(record::MINUTES_PER_DAY) as usize] += 1;
+            }
+        }
+
+        minutes
+    }
 }
 
 impl Shift {
-    fn new(date: String) -> Self {
+    fn new(date: Date) -> Self {
         Self {
             date,
             entries: Vec::new(),
         }
     }
 
-    fn push_entry(&mut self, start: u8, end: u8, consciousness: Consciousness, line: &str) {
-        let entry = Entry::new(start, end, consciousness, line);
+    fn push_entry(&mut self, start: i32, end: i32, consciousness: Consciousness) {
+        let entry = Entry::new(start, end, consciousness);
         self.entries.push(entry);
     }
 
-    fn time_asleep(&self) -> u32 {
+    fn is_asleep(&self, minute: i32) -> bool {
         self.entries
             .iter()
-            .map(|x| x.time_asleep())
-            .sum()
+            .filter(|x| x.consciousness == Consciousness::Asleep)
+            .any(|x| x.time_start <= minute && minute < x.time_end)
     }
 }
 
 impl Entry {
-    fn new(start: u8, end: u8, consciousness: Consciousness, _line: &str) -> Self {
+    fn new(start: i32, end: i32, consciousness: Consciousness) -> Self {
         Self {
             time_start: start,
             time_end: end,
             consciousness,
         }
     }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn time_asleep(&self) -> u32 {
-        match self.consciousness {
-            Consciousness::Asleep => {
-                (self.time_end - self.time_start) as u32
-            },
-            Consciousness::Awake => {
-                0
-            }
-        }
+    fn load(name: &str) -> Result<Vec<Record>> {
+        let path = format!("./input/malformed/{}", name);
+        parse_records(io::BufReader::new(fs::File::open(path)?))
+    }
+
+    // (problem, line) for everything the validator finds
+    fn issues(name: &str, window: Window) -> Vec<(Problem, usize)> {
+        validate::validate(&load(name).unwrap(), window == Window::Midnight)
+            .into_iter()
+            .map(|x| (x.problem, x.line_no))
+            .collect()
+    }
+
+    fn solve(name: &str, orphans: OrphanPolicy, window: Window) -> Result<(u32, u32)> {
+        let records = load(name)?;
+        check_records(&records, orphans, window)?;
+        let guards = build_guards(records);
+
+        Ok((part1(&guards, window)?, part2(&guards, window)?))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn leading_orphans() {
+        let name = "leading_orphans.txt";
+        assert_eq!(issues(name, Window::Midnight), [(Problem::NoShift, 1), (Problem::NoShift, 2)]);
+        assert!(solve(name, OrphanPolicy::Error, Window::Midnight).is_err());
+        assert_eq!(solve(name, OrphanPolicy::Skip, Window::Midnight).unwrap(), (3960, 3960));
+    }
+
+    #[test]
+    fn only_orphans() {
+        let name = "only_orphans.txt";
+        assert!(solve(name, OrphanPolicy::Error, Window::Midnight).is_err());
+
+        // skipping every line passes the checks, but leaves no guards
+        let records = load(name).unwrap();
+        assert!(check_records(&records, OrphanPolicy::Skip, Window::Midnight).is_ok());
+        assert!(build_guards(records).is_empty());
+    }
+
+    #[test]
+    fn unsorted() {
+        assert_eq!(solve("unsorted.txt", OrphanPolicy::Error, Window::Midnight).unwrap(), (50, 50));
+    }
+
+    #[test]
+    fn loose_formatting() {
+        assert_eq!(solve("loose_formatting.txt", OrphanPolicy::Error, Window::Midnight).unwrap(), (50, 50));
+    }
+
+    #[test]
+    fn shift_without_events() {
+        assert_eq!(solve("shift_without_events.txt", OrphanPolicy::Error, Window::Midnight).unwrap(), (3960, 3960));
+    }
+
+    #[test]
+    fn unknown_event() {
+        let err = load("unknown_event.txt").unwrap_err().to_string();
+        assert!(err.starts_with("Line 3:"), "{}", err);
+    }
+
+    #[test]
+    fn double_sleep() {
+        assert_eq!(issues("double_sleep.txt", Window::Midnight), [(Problem::AlreadyAsleep, 3)]);
+        assert!(solve("double_sleep.txt", OrphanPolicy::Skip, Window::Midnight).is_err());
+    }
+
+    #[test]
+    fn double_wake() {
+        assert_eq!(issues("double_wake.txt", Window::Midnight), [(Problem::AlreadyAwake, 4)]);
+        assert!(solve("double_wake.txt", OrphanPolicy::Skip, Window::Midnight).is_err());
+    }
+
+    #[test]
+    fn wake_without_sleep() {
+        assert_eq!(issues("wake_without_sleep.txt", Window::Midnight), [(Problem::AlreadyAwake, 2)]);
+        assert!(solve("wake_without_sleep.txt", OrphanPolicy::Skip, Window::Midnight).is_err());
+    }
+
+    #[test]
+    fn hour_boundaries() {
+        let name = "hour_boundaries.txt";
+        assert_eq!(issues(name, Window::Midnight), [(Problem::OutsideWindow, 3), (Problem::OutsideWindow, 5)]);
+        assert!(solve(name, OrphanPolicy::Error, Window::Midnight).is_err());
+        assert_eq!(solve(name, OrphanPolicy::Error, Window::Day).unwrap(), (500, 500));
+    }
+
+    #[test]
+    fn multi_day() {
+        let name = "multi_day.txt";
+        let outside = [2, 3, 5, 6].map(|x| (Problem::OutsideWindow, x));
+        assert_eq!(issues(name, Window::Midnight), outside);
+        assert!(solve(name, OrphanPolicy::Error, Window::Midnight).is_err());
+        assert_eq!(solve(name, OrphanPolicy::Error, Window::Day).unwrap(), (27600, 27600));
+    }
+
+    #[test]
+    fn multi_day_stats() {
+        // guard #20 is asleep from 23:00 until 23:45 the next day
+        let guards = build_guards(load("multi_day.txt").unwrap());
+        let guard = guards.iter().find(|x| x.id == 20).unwrap();
+        assert_eq!(guard.total_sleep(Window::Day), 24 * 60 + 45);
+        assert_eq!(guard.longest_nap(Window::Day), 24 * 60 + 45);
+        assert_eq!(guard.asleep_probability(Window::Day, 23 * 60 + 50), 1.0);
+        assert_eq!(guard.sleepiest_minute(Window::Day).count, 2);
+
+        // the midnight hour only sees 00:00-00:59 of the shift date
+        assert_eq!(guard.total_sleep(Window::Midnight), 60);
+        assert_eq!(guard.longest_nap(Window::Midnight), 60);
+    }
+
+    #[test]
+    fn ties_go_to_the_lowest_id_then_the_earliest_minute() {
+        // guards #10 and #20 both sleep for 20 minutes, never on the same
+        // minute twice; #20's naps start earlier, but the lower id wins
+        let buf = io::BufReader::new(fs::File::open("./input/ties.txt").unwrap());
+        let records = parse_records(buf).unwrap();
+        check_records(&records, OrphanPolicy::Error, Window::Midnight).unwrap();
+        let guards = build_guards(records);
+
+        assert_eq!(part1(&guards, Window::Midnight).unwrap(), 10 * 30);
+        assert_eq!(part2(&guards, Window::Midnight).unwrap(), 10 * 30);
+
+        let mut reversed = guards.clone();
+        reversed.reverse();
+        assert_eq!(part1(&reversed, Window::Midnight).unwrap(), 10 * 30);
+        assert_eq!(part2(&reversed, Window::Midnight).unwrap(), 10 * 30);
+    }
+
+    #[test]
+    fn sleepiest_minute_ties_go_to_the_earliest() {
+        assert_eq!(sleepiest_minute(&[0, 2, 1, 2]), (1, 2));
+        assert_eq!(sleepiest_minute(&[0, 0, 0]), (0, 0));
+        assert_eq!(sleepiest_minute(&[]), (0, 0));
+    }
+}
@@ -0,0 +1,210 @@
+use std::error;
+use std::fmt;
+use std::result;
+use std::str::FromStr;
+
+type Result<T> = result::Result<T, Box<dyn error::Error>>;
+
+pub const MINUTES_PER_DAY: i32 = 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub date: Date,
+    pub hour: u8,
+    pub minute: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    BeginShift(u32),
+    FallsAsleep,
+    WakesUp,
+}
+
+/// A single line of the guard log, e.g. `[1518-11-01 00:05] falls asleep`.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub timestamp: Timestamp,
+    pub event: Event,
+    /// 1-based line number in the input (0 if unknown)
+    pub line_no: usize,
+    pub line: String,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u8, day: u8) -> Result<Self> {
+        if !(1..=12).contains(&month) {
+            return Err(format!("Invalid month: {}", month).into());
+        }
+        if day < 1 || day > days_in_month(year, month) {
+            return Err(format!("Invalid day: {}-{:02}-{:02}", year, month, day).into());
+        }
+
+        Ok(Self { year, month, day })
+    }
+
+    /// The following calendar day.
+    pub fn succ(self) -> Self {
+        if self.day < days_in_month(self.year, self.month) {
+            Self { day: self.day + 1, ..self }
+        }
+        else if self.month < 12 {
+            Self { month: self.month + 1, day: 1, ..self }
+        }
+        else {
+            Self { year: self.year + 1, month: 1, day: 1 }
+        }
+    }
+
+    /// Number of days since 1970-01-01 (negative before then).
+    ///
+    /// See: http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    pub fn days(&self) -> i64 {
+        let (m, d) = (self.month as i64, self.day as i64);
+        let y = self.year as i64 - if m <= 2 { 1 } else { 0 };
+        let era = if y >= 0 { y } else { y - 399 } / 400;
+        let yoe = y - era * 400;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+        era * 146097 + doe - 719468
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Timestamp {
+    /// Minutes since midnight of this timestamp's own date.
+    pub fn minute_of_day(&self) -> i32 {
+        self.hour as i32 * 60 + self.minute as i32
+    }
+
+    /// Minutes since midnight at the *start* of `date`; this is negative for
+    /// timestamps on earlier days (e.g. a shift starting at 23:58).
+    pub fn minutes_since(&self, date: Date) -> i32 {
+        let days = (self.date.days() - date.days()) as i32;
+        days * MINUTES_PER_DAY + self.minute_of_day()
+    }
+}
+
+impl FromStr for Date {
+    type Err = Box<dyn error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.trim().splitn(3, '-');
+        let mut part = |name: &str| -> Result<&str> {
+            parts.next()
+                .filter(|x| !x.is_empty())
+                .ok_or_else(|| format!("Could not find {} in date: {}", name, s).into())
+        };
+
+        let year = part("year")?.parse()?;
+        let month = part("month")?.parse()?;
+        let day = part("day")?.parse()?;
+
+        Date::new(year, month, day)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = Box<dyn error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // accept either `YYYY-MM-DD HH:MM` or `YYYY-MM-DDTHH:MM`, with any
+        // amount of whitespace, single-digit hours and optional seconds
+        let s = s.trim();
+        let (date, time) = s
+            .split_once(|c: char| c.is_whitespace() || c == 'T')
+            .ok_or_else(|| format!("Could not find a time in timestamp: {}", s))?;
+
+        let date = date.parse()?;
+        let mut time = time.trim().split(':');
+        let hour: u8 = time.next()
+            .ok_or_else(|| format!("Could not find hour in timestamp: {}", s))?
+            .parse()?;
+        let minute: u8 = time.next()
+            .ok_or_else(|| format!("Could not find minute in timestamp: {}", s))?
+            .parse()?;
+        let _seconds: Option<u8> = time.next().map(|x| x.parse()).transpose()?;
+        if time.next().is_some() || hour > 23 || minute > 59 {
+            return Err(format!("Invalid time in timestamp: {}", s).into());
+        }
+
+        Ok(Self { date, hour, minute })
+    }
+}
+
+impl FromStr for Event {
+    type Err = Box<dyn error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let lower = s.trim().to_ascii_lowercase();
+        if lower.contains("falls asleep") {
+            Ok(Event::FallsAsleep)
+        }
+        else if lower.contains("wakes up") {
+            Ok(Event::WakesUp)
+        }
+        else if let Some((_, rest)) = lower.split_once('#') {
+            let id = rest
+                .split(|c: char| !c.is_ascii_digit())
+                .next()
+                .filter(|x| !x.is_empty())
+                .ok_or_else(|| format!("Could not find guard id in: {}", s))?
+                .parse()?;
+            Ok(Event::BeginShift(id))
+        }
+        else {
+            Err(format!("Invalid entry line, should contain 'falls asleep', 'wakes up' or a guard id: {}", s.trim()).into())
+        }
+    }
+}
+
+impl FromStr for Record {
+    type Err = Box<dyn error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let line = s.trim();
+        let (timestamp, event) = line
+            .strip_prefix('[')
+            .and_then(|x| x.split_once(']'))
+            .ok_or_else(|| format!("Could not find a [timestamp] in line: {}", s))?;
+
+        Ok(Self {
+            timestamp: timestamp.parse()?,
+            event: event.parse()?,
+            line_no: 0,
+            line: line.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:02}:{:02}", self.date, self.hour, self.minute)
+    }
+}
@@ -0,0 +1,66 @@
+use std::io::Write;
+
+use crate::record::Date;
+use crate::{Guard, Result, Shift};
+
+/// Which shifts to include in the grid; `None` means "don't filter".
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub guard: Option<u32>,
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+}
+
+impl Filter {
+    fn matches(&self, guard: &Guard, shift: &Shift) -> bool {
+        self.guard.is_none_or(|x| x == guard.id)
+        && self.from.is_none_or(|x| shift.date >= x)
+        && self.to.is_none_or(|x| shift.date <= x)
+    }
+}
+
+/// Write out the shifts in the same format as the puzzle's example, e.g.:
+///
+/// ```text
+/// Date   ID   Minute
+///             000000000011111111112222222222333333333344444444445555555555
+///             012345678901234567890123456789012345678901234567890123456789
+/// 11-01  #10  .....####################.....#########################.....
+/// ```
+pub fn write_grid<W: Write>(mut out: W, guards: &[Guard], filter: &Filter) -> Result<()> {
+    let mut shifts = guards
+        .iter()
+        .flat_map(|g| g.shifts.iter().map(move |s| (g, s)))
+        .filter(|(g, s)| filter.matches(g, s))
+        .collect::<Vec<_>>();
+    shifts.sort_by_key(|(g, s)| (s.date, g.id));
+
+    let id_width = shifts
+        .iter()
+        .map(|(g, _)| g.id.to_string().len() + 1)
+        .max()
+        .unwrap_or(0)
+        .max(2);
+    let pad = " ".repeat(5 + 2 + id_width + 2);
+
+    writeln!(out, "Date   {:w$}  Minute", "ID", w = id_width)?;
+    writeln!(out, "{}{}", pad, (0..60).map(|m| (b'0' + m / 10) as char).collect::<String>())?;
+    writeln!(out, "{}{}", pad, (0..60).map(|m| (b'0' + m % 10) as char).collect::<String>())?;
+
+    for (guard, shift) in shifts {
+        let minutes = (0..60)
+            .map(|m| if shift.is_asleep(m) { '#' } else { '.' })
+            .collect::<String>();
+        writeln!(
+            out,
+            "{:02}-{:02}  {:w$}  {}",
+            shift.date.month,
+            shift.date.day,
+            format!("#{}", guard.id),
+            minutes,
+            w = id_width,
+        )?;
+    }
+
+    Ok(())
+}
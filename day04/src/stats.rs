@@ -0,0 +1,246 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::record::{Date, MINUTES_PER_DAY};
+use crate::{Consciousness, Guard, Result, Shift, Window};
+
+const MINUTES: i32 = 60;
+
+/// The minute(s) a guard was most often asleep, and how often that was.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SleepiestMinute {
+    pub count: u32,
+    /// Every minute with the top count, in ascending order; empty if the
+    /// guard never slept
+    pub minutes: Vec<u32>,
+}
+
+/// A statistic that guards can be ranked by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    TotalSleep,
+    SleepPerShift,
+    SleepiestMinute,
+    AsleepAt(u32),
+    LongestNap,
+}
+
+// note: all of the queries below only look at the minutes inside `window`;
+// for the midnight hour, anything before 00:00 or after 00:59 is clipped
+impl Guard {
+    /// Total minutes asleep, over every shift.
+    pub fn total_sleep(&self, window: Window) -> u32 {
+        self.shifts
+            .iter()
+            .map(|x| shift_sleep(x, window))
+            .sum()
+    }
+
+    /// Minutes asleep for each shift, in the order the shifts were worked.
+    pub fn sleep_per_shift(&self, window: Window) -> Vec<(Date, u32)> {
+        self.shifts
+            .iter()
+            .map(|x| (x.date, shift_sleep(x, window)))
+            .collect()
+    }
+
+    /// Average minutes asleep per shift.
+    pub fn mean_sleep_per_shift(&self, window: Window) -> f64 {
+        match self.shifts.len() {
+            0 => 0.0,
+            n => self.total_sleep(window) as f64 / n as f64,
+        }
+    }
+
+    pub fn sleepiest_minute(&self, window: Window) -> SleepiestMinute {
+        let counts = window.histogram(self);
+        let count = counts.iter().copied().max().unwrap_or(0);
+        let minutes = match count {
+            0 => Vec::new(),
+            _ => (0..counts.len() as u32).filter(|&m| counts[m as usize] == count).collect(),
+        };
+
+        SleepiestMinute { count, minutes }
+    }
+
+    /// Fraction of shifts where the guard was asleep at `minute` (of the
+    /// window).
+    pub fn asleep_probability(&self, window: Window, minute: u32) -> f64 {
+        if self.shifts.is_empty() {
+            return 0.0;
+        }
+
+        let asleep = self.shifts
+            .iter()
+            .filter(|x| naps(x, window).any(|(start, end)| covers(window, start, end, minute as i32)))
+            .count();
+
+        asleep as f64 / self.shifts.len() as f64
+    }
+
+    /// The longest continuous stretch of sleep, in minutes.
+    pub fn longest_nap(&self, window: Window) -> u32 {
+        self.shifts
+            .iter()
+            .flat_map(|x| naps(x, window))
+            .map(|(start, end)| (end - start) as u32)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+fn shift_sleep(shift: &Shift, window: Window) -> u32 {
+    naps(shift, window)
+        .map(|(start, end)| (end - start) as u32)
+        .sum()
+}
+
+// the stretches of sleep in a shift, as half-open intervals of minutes
+// since midnight of the shift date; back-to-back entries are merged into a
+// single nap, and everything is clipped to the window
+fn naps(shift: &Shift, window: Window) -> impl Iterator<Item = (i32, i32)> {
+    let mut naps: Vec<(i32, i32)> = Vec::new();
+    let asleep = shift.entries
+        .iter()
+        .filter(|x| x.consciousness == Consciousness::Asleep);
+    for entry in asleep {
+        let (start, end) = match window {
+            Window::Midnight => (entry.time_start.max(0), entry.time_end.min(MINUTES)),
+            Window::Day => (entry.time_start, entry.time_end),
+        };
+        if start >= end {
+            continue
+        }
+
+        match naps.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => naps.push((start, end)),
+        }
+    }
+
+    naps.into_iter()
+}
+
+// whether the (already clipped) nap start..end includes `minute` of the
+// window; a day-long window wraps around, so any day counts
+fn covers(window: Window, start: i32, end: i32, minute: i32) -> bool {
+    match window {
+        Window::Midnight => start <= minute && minute < end,
+        Window::Day => {
+            let first = start + (minute - start).rem_euclid(MINUTES_PER_DAY);
+            first < end
+        },
+    }
+}
+
+impl Metric {
+    pub fn value(&self, guard: &Guard, window: Window) -> f64 {
+        match *self {
+            Metric::TotalSleep => guard.total_sleep(window) as f64,
+            Metric::SleepPerShift => guard.mean_sleep_per_shift(window),
+            Metric::SleepiestMinute => guard.sleepiest_minute(window).count as f64,
+            Metric::AsleepAt(minute) => guard.asleep_probability(window, minute),
+            Metric::LongestNap => guard.longest_nap(window) as f64,
+        }
+    }
+}
+
+/// Rank the guards by `metric` (highest first, ties broken by guard id), and
+/// write out a table of the top `n`.
+pub fn write_top<W: Write>(mut out: W, guards: &[Guard], metric: Metric, window: Window, n: usize) -> Result<()> {
+    if let Metric::AsleepAt(minute) = metric {
+        if window == Window::Midnight && minute >= MINUTES as u32 {
+            return Err(format!("Minute should be in 0..{} for the midnight hour: {}", MINUTES, minute).into());
+        }
+    }
+
+    let mut ranked = guards
+        .iter()
+        .map(|x| (metric.value(x, window), x))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.id.cmp(&b.1.id)));
+
+    write!(out, "Rank  Guard    Total  Per Shift  Longest Nap  Sleepiest Minute")?;
+    if let Metric::AsleepAt(minute) = metric {
+        write!(out, "  P(Asleep at {:02}:{:02})", minute / 60, minute % 60)?;
+    }
+    writeln!(out)?;
+
+    for (rank, (_, guard)) in ranked.into_iter().take(n).enumerate() {
+        let sleepiest = guard.sleepiest_minute(window);
+        let sleepiest = match sleepiest.count {
+            0 => "-".to_string(),
+            _ => format!("{} (x{})", join(&sleepiest.minutes, window), sleepiest.count),
+        };
+
+        write!(
+            out,
+            "{:<4}  {:<7}  {:>5}  {:>9.2}  {:>11}  {}",
+            rank + 1,
+            format!("#{}", guard.id),
+            guard.total_sleep(window),
+            guard.mean_sleep_per_shift(window),
+            guard.longest_nap(window),
+            sleepiest,
+        )?;
+        if let Metric::AsleepAt(minute) = metric {
+            write!(out, "  {:.3}", guard.asleep_probability(window, minute))?;
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+// minutes of the midnight hour are written as-is, and minutes of the day
+// as HH:MM
+fn join(minutes: &[u32], window: Window) -> String {
+    minutes
+        .iter()
+        .map(|x| match window {
+            Window::Midnight => x.to_string(),
+            Window::Day => format!("{:02}:{:02}", x / 60, x % 60),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl FromStr for Metric {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once('=') {
+            Some(("asleep-at", minute)) => {
+                let minute = minute.parse()?;
+                if minute >= MINUTES_PER_DAY as u32 {
+                    return Err(format!("Minute should be in 0..{}: {}", MINUTES_PER_DAY, minute).into());
+                }
+                Ok(Metric::AsleepAt(minute))
+            },
+            _ => match s {
+                "total" => Ok(Metric::TotalSleep),
+                "per-shift" => Ok(Metric::SleepPerShift),
+                "minute" => Ok(Metric::SleepiestMinute),
+                "nap" => Ok(Metric::LongestNap),
+                _ => Err(format!("Unknown metric (expected total, per-shift, minute, nap or asleep-at=M): {}", s).into()),
+            },
+        }
+    }
+}
+
+/// Write out every statistic for a single guard, including each shift.
+pub fn write_summary<W: Write>(mut out: W, guard: &Guard, window: Window) -> Result<()> {
+    let sleepiest = guard.sleepiest_minute(window);
+
+    writeln!(out, "Guard #{}", guard.id)?;
+    writeln!(out, "  Shifts:           {}", guard.shifts.len())?;
+    writeln!(out, "  Total sleep:      {}", guard.total_sleep(window))?;
+    writeln!(out, "  Sleep per shift:  {:.2}", guard.mean_sleep_per_shift(window))?;
+    writeln!(out, "  Longest nap:      {}", guard.longest_nap(window))?;
+    writeln!(out, "  Sleepiest minute: {} (x{})", join(&sleepiest.minutes, window), sleepiest.count)?;
+    for (date, sleep) in guard.sleep_per_shift(window) {
+        writeln!(out, "  {}  {:>2}", date, sleep)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use crate::record::{Date, Event, Record};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// A sleep/wake event before any guard has begun a shift
+    NoShift,
+    /// "falls asleep" while the guard is already asleep
+    AlreadyAsleep,
+    /// "wakes up" while the guard is already awake
+    AlreadyAwake,
+    /// A sleep/wake event outside of the 00:00-00:59 window
+    OutsideWindow,
+    /// The shift ended (new shift or end of log) with the guard still asleep
+    EndedAsleep,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub problem: Problem,
+    pub line_no: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    NoShift,
+    Awake,
+    Asleep,
+}
+
+/// Run every event in the (sorted) log through an Awake/Asleep state
+/// machine, and report anything that doesn't line up. Events outside of the
+/// midnight hour are only reported if `midnight_only` is set.
+pub fn validate(records: &[Record], midnight_only: bool) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut state = State::NoShift;
+    let mut shift_date: Option<Date> = None;
+    let mut last_record: Option<&Record> = None;
+
+    for record in records {
+        state = match (state, record.event) {
+            (State::Asleep, Event::BeginShift(_)) => {
+                // blame the line where the previous guard fell asleep
+                issues.push(Issue::new(Problem::EndedAsleep, last_record.unwrap()));
+                State::Awake
+            },
+            (_, Event::BeginShift(_)) => State::Awake,
+            (State::NoShift, _) => {
+                issues.push(Issue::new(Problem::NoShift, record));
+                State::NoShift
+            },
+            (State::Awake, Event::FallsAsleep) => State::Asleep,
+            (State::Asleep, Event::FallsAsleep) => {
+                issues.push(Issue::new(Problem::AlreadyAsleep, record));
+                State::Asleep
+            },
+            (State::Asleep, Event::WakesUp) => State::Awake,
+            (State::Awake, Event::WakesUp) => {
+                issues.push(Issue::new(Problem::AlreadyAwake, record));
+                State::Awake
+            },
+        };
+
+        match record.event {
+            Event::BeginShift(_) => {
+                shift_date = Some(crate::shift_date(record));
+            },
+            _ if midnight_only && state != State::NoShift => {
+                let minute = record.timestamp.minutes_since(shift_date.unwrap());
+                if !(0..60).contains(&minute) {
+                    issues.push(Issue::new(Problem::OutsideWindow, record));
+                }
+            },
+            _ => {},
+        }
+
+        last_record = Some(record);
+    }
+
+    if state == State::Asleep {
+        issues.push(Issue::new(Problem::EndedAsleep, last_record.unwrap()));
+    }
+
+    issues
+}
+
+impl Issue {
+    fn new(problem: Problem, record: &Record) -> Self {
+        Self {
+            problem,
+            line_no: record.line_no,
+            line: record.line.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Problem::NoShift => "entry before any guard has begun a shift",
+            Problem::AlreadyAsleep => "falls asleep, but the guard is already asleep",
+            Problem::AlreadyAwake => "wakes up, but the guard is already awake",
+            Problem::OutsideWindow => "entry is outside of the midnight hour (00:00-00:59)",
+            Problem::EndedAsleep => "shift ended while the guard was still asleep",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}: {}", self.line_no, self.problem, self.line)
+    }
+}
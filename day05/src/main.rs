@@ -1,87 +1,267 @@
+use std::collections::BTreeSet;
+use std::env;
 use std::error;
 use std::fs;
 use std::result;
+use std::thread;
 use std::io::{self, BufRead};
 
+mod parallel;
+mod polymer;
+mod reduce;
+mod rules;
+mod trace;
+
+use rules::Rule;
+
 const INPUT_FILE: &str = "./input/polymer.txt";
 
 type Result<T> = result::Result<T, Box<dyn error::Error>>;
 
+#[derive(Debug, Default)]
+struct Options {
+    stdin: bool,
+    quiet: bool,
+    threads: Option<usize>,
+    rule: Option<String>,
+    trace: Option<String>,
+    interactive: bool,
+}
+
 fn main() -> Result<()> {
-    let buf = io::BufReader::new(fs::File::open(&INPUT_FILE)?);
+    let opts = parse_args(env::args().skip(1))?;
+    let rule: Box<dyn Rule> = match opts.rule.as_deref() {
+        None | Some("classic") => Box::new(rules::Classic),
+        Some("unicode") => Box::new(rules::UnicodeCase),
+        Some(path) => {
+            let buf = io::BufReader::new(fs::File::open(path)?);
+            Box::new(rules::PairTable::from_reader(buf)?)
+        },
+    };
+    let rule = rule.as_ref();
+
+    if opts.interactive {
+        let stdin = io::stdin();
+        return interactive(stdin.lock(), rule);
+    }
 
-    let p1 = part1(buf)?;
-    let p2 = part2(&p1)?;
+    if let Some(format) = &opts.trace {
+        // tracing only covers part 1, and replaces the normal output
+        let trace = if opts.stdin {
+            let stdin = io::stdin();
+            trace::reduce_traced(stdin.lock(), rule)?
+        }
+        else {
+            trace::reduce_traced(io::BufReader::new(fs::File::open(INPUT_FILE)?), rule)?
+        };
+
+        let stdout = io::stdout();
+        return match format.as_str() {
+            "log" => trace.write_log(stdout.lock()),
+            "json" => trace.write_json(stdout.lock()),
+            _ => Err(format!("Unknown trace format (expected log or json): {}", format).into()),
+        };
+    }
+
+    let p1 = if opts.stdin {
+        let stdin = io::stdin();
+        part1(stdin.lock(), opts.threads.unwrap_or(1), rule)?
+    }
+    else {
+        part1(io::BufReader::new(fs::File::open(INPUT_FILE)?), opts.threads.unwrap_or(1), rule)?
+    };
+    // note: part 1 streams the input by default, but the part 2 search is
+    // always worth spreading across threads
+    let threads = opts.threads
+        .or_else(|| thread::available_parallelism().ok().map(|x| x.get()))
+        .unwrap_or(1);
+    let (removed, p2) = part2(&p1, threads, rule)?;
 
     println!("Part 1: {}", p1.len());
-    println!("Part 1: {}", p2.len());
+    let removed = match removed {
+        Some(unit_type) => unit_type_name(&p1, unit_type, rule),
+        None => "none".to_string(),
+    };
+    println!("Part 2: {} (removed {})", p2.len(), removed);
 
-    println!("\n\n");
-    println!("Polymer #1:\n{}", p1);
-    println!("Polymer #2:\n{}", p2);
+    if !opts.quiet {
+        println!("\n\n");
+        println!("Polymer #1:\n{}", p1.iter().collect::<String>());
+        println!("Polymer #2:\n{}", p2.iter().collect::<String>());
+    }
 
     Ok(())
 }
 
-fn does_react(a: &char, b: &char) -> bool {
-    // this is a bit ugly...
-    (
-        a.is_ascii_lowercase() && b.is_ascii_uppercase()
-        ||
-        a.is_ascii_uppercase() && b.is_ascii_lowercase()
-    )
-    && 
-    a.to_ascii_uppercase() == b.to_ascii_uppercase()
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options> {
+    let mut opts = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // read the polymer from stdin instead of the input file
+            "--stdin" => opts.stdin = true,
+            // only print the lengths, not the polymers themselves
+            "--quiet" => opts.quiet = true,
+            // reduce the polymer in parallel chunks
+            "--threads" => {
+                opts.threads = Some(args.next().ok_or("--threads requires a number")?.parse()?);
+            },
+            // `classic` (the default), `unicode`, or a file of reacting pairs
+            "--rule" => {
+                opts.rule = Some(args.next().ok_or("--rule requires a rule name or path")?);
+            },
+            // write out every reaction in part 1, as a `log` or `json`
+            "--trace" => {
+                opts.trace = Some(args.next().ok_or("--trace requires a format")?);
+            },
+            // build up a polymer from stdin, one line at a time
+            "--interactive" => opts.interactive = true,
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
+
+    Ok(opts)
 }
 
-fn reduce<I>(it: I) -> Result<String> where I: Iterator<Item = char> {
-    let mut output: Vec<char> = Vec::new();
-    let mut last_char: Option<char> = None;
-    for c in it {
-        // 1st case: current and last char match
-        if last_char.is_some() && does_react(&c, &last_char.unwrap()) {
-            last_char = output.pop();
-            continue
+fn interactive<B: BufRead, R: Rule + ?Sized>(buf: B, rule: &R) -> Result<()> {
+    // each line is either `undo [N]`, or more units to add to the polymer
+    let mut polymer = polymer::Polymer::new(rule);
+    for line in buf.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("undo"), n) => {
+                let n = n.map(|x| x.parse()).transpose()?.unwrap_or(1);
+                for _ in 0..n {
+                    if !polymer.undo_last() {
+                        println!("Nothing left to undo");
+                        break
+                    }
+                }
+            },
+            _ => {
+                polymer.extend(line.chars().filter(|x| !x.is_whitespace()));
+            },
         }
-        // 2nd case: current doesn't match the previous or next
-        // the `last_char` should be added to the output array
-        if last_char.is_some() {
-            output.push(last_char.unwrap());
-        }
-        last_char = Some(c);
+
+        println!(
+            "Units: {}, Length: {}, Polymer: {}",
+            polymer.units_pushed(),
+            polymer.current_len(),
+            polymer.units().iter().collect::<String>(),
+        );
     }
-    // finally, we should push the last processed char
-    if last_char.is_some() {
-        output.push(last_char.unwrap());
+
+    Ok(())
+}
+
+fn unit_type_name<R: Rule + ?Sized>(polymer: &[char], unit_type: char, rule: &R) -> String {
+    // list every unit that was removed along with this type, e.g. "a/A"
+    let others = polymer
+        .iter()
+        .copied()
+        .filter(|&x| x != unit_type && rule.unit_type(x) == unit_type)
+        .collect::<BTreeSet<_>>();
+
+    [unit_type]
+        .into_iter()
+        .chain(others)
+        .map(String::from)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn part1<B: BufRead, R: Rule + ?Sized>(buf: B, threads: usize, rule: &R) -> Result<Vec<char>> {
+    if threads <= 1 {
+        return reduce::reduce_reader(buf, rule);
     }
 
-    Ok(output.into_iter().collect())
+    // the parallel reducer needs the whole polymer up front
+    let mut polymer = Vec::new();
+    reduce::for_each_unit(buf, |x| polymer.push(x))?;
+
+    Ok(parallel::reduce_parallel(&polymer, threads, rule))
 }
 
-fn part1<B: BufRead>(buf: B) -> Result<String> {
-    let iter = buf
-        .bytes()
-        .flatten()
-        .map(|x| x as char);
-        
-    reduce(iter)
+fn part2<R: Rule + ?Sized>(polymer: &[char], threads: usize, rule: &R) -> Result<(Option<char>, Vec<char>)> {
+    // only try removing the unit types that actually occur in the polymer
+    let unit_types = polymer
+        .iter()
+        .map(|&x| rule.unit_type(x))
+        .collect::<BTreeSet<char>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    if unit_types.is_empty() {
+        // the polymer already fully reacted, so there's nothing to remove
+        return Ok((None, Vec::new()));
+    }
+
+    // split the unit types between the threads; each thread returns the
+    // best polymer it found
+    let threads = threads.clamp(1, unit_types.len());
+    let per_thread = unit_types.len().div_ceil(threads);
+    let results = thread::scope(|s| {
+        let handles = unit_types
+            .chunks(per_thread)
+            .map(|chunk| s.spawn(move || {
+                chunk
+                    .iter()
+                    .map(|&test_unit| {
+                        let iter = polymer
+                            .iter()
+                            .copied()
+                            .filter(|&x| rule.unit_type(x) != test_unit);
+                        (test_unit, reduce::reduce(iter, rule))
+                    })
+                    .min_by_key(|(unit, res)| (res.len(), *unit))
+            }))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|x| x.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    // shortest polymer wins, with ties going to the earliest unit type
+    results
+        .into_iter()
+        .min_by_key(|(unit, res)| (res.len(), *unit))
+        .map(|(unit, res)| (Some(unit), res))
+        .ok_or("No polymers found!".into())
 }
 
-fn part2(polymer: &str) -> Result<String> {
-    let mut min = usize::MAX;
-    let mut new_polymer: Option<String> = None;
-    for test_char in 65u8..=90u8 {
-        let test_char = test_char as char;
-        let iter = polymer
-            .chars()
-            .filter(|x| x.to_ascii_uppercase() != test_char);
-        
-        let res = reduce(iter)?;
-        if res.len() <= min {
-            min = res.len();
-            new_polymer = Some(res);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Vec<char> {
+        part1("dabAcCaCBAcCcaDA".as_bytes(), 1, &rules::Classic).unwrap()
+    }
+
+    #[test]
+    fn the_puzzle_example() {
+        let polymer = example();
+        assert_eq!(polymer.iter().collect::<String>(), "dabCBAcaDA");
+
+        let (removed, p2) = part2(&polymer, 1, &rules::Classic).unwrap();
+        assert_eq!(removed, Some('c'));
+        assert_eq!(p2.iter().collect::<String>(), "daDA");
+        assert_eq!(unit_type_name(&polymer, 'c', &rules::Classic), "c/C");
+    }
+
+    #[test]
+    fn more_threads_than_unit_types() {
+        let polymer = example();
+        for threads in [0, 2, 3, 4, 64] {
+            let (removed, p2) = part2(&polymer, threads, &rules::Classic).unwrap();
+            assert_eq!((removed, p2.len()), (Some('c'), 4), "{} threads", threads);
         }
     }
-    
-    new_polymer.ok_or("No polymers found!".into())
-}
\ No newline at end of file
+
+    #[test]
+    fn fully_reacted() {
+        let polymer = part1("aAbBcC\n".as_bytes(), 1, &rules::Classic).unwrap();
+        assert!(polymer.is_empty());
+        assert_eq!(part2(&polymer, 4, &rules::Classic).unwrap(), (None, Vec::new()));
+    }
+}
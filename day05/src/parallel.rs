@@ -0,0 +1,130 @@
+use std::thread;
+
+use crate::reduce::reduce;
+use crate::rules::Rule;
+
+/// Reduce a polymer using up to `threads` threads.
+///
+/// Reduction is associative, so the polymer can be split into chunks which
+/// are each reduced on their own thread. The reduced chunks can't react
+/// internally any more, so merging two of them only has to deal with the
+/// units where they meet.
+pub fn reduce_parallel<R: Rule + ?Sized>(polymer: &[char], threads: usize, rule: &R) -> Vec<char> {
+    let threads = threads.clamp(1, polymer.len().max(1));
+    if threads == 1 {
+        return reduce(polymer.iter().copied(), rule);
+    }
+
+    let chunk_size = polymer.len().div_ceil(threads);
+    let reduced = thread::scope(|s| {
+        let handles = polymer
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move || reduce(chunk.iter().copied(), rule)))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|x| x.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    reduced
+        .into_iter()
+        .reduce(|left, right| merge(left, right, rule))
+        .unwrap_or_default()
+}
+
+/// Join two already-reduced polymers, reacting away any units at the
+/// boundary.
+pub fn merge<R: Rule + ?Sized>(mut left: Vec<char>, right: Vec<char>, rule: &R) -> Vec<char> {
+    let mut skip = 0;
+    while let (Some(&l), Some(&r)) = (left.last(), right.get(skip)) {
+        if !rule.reacts(l, r) {
+            break
+        }
+        left.pop();
+        skip += 1;
+    }
+    left.extend_from_slice(&right[skip..]);
+
+    left
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Classic, PairTable, UnicodeCase};
+
+    /// Tiny PRNG, so the tests don't need any extra dependencies.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    /// Compare `reduce_parallel` against the plain sequential `reduce` on
+    /// `rounds` random polymers, using a range of lengths and thread counts.
+    /// Each polymer is made from the first few of `units` (keeping the
+    /// number of units small, so there's plenty to react).
+    fn assert_matches_sequential<R: Rule + ?Sized>(rule: &R, units: &[char], rounds: usize, seed: u64) {
+        let mut rng = XorShift(seed.max(1));
+        for _ in 0..rounds {
+            let len = (rng.next() % 2000) as usize;
+            let used = 1 + (rng.next() % units.len() as u64) as usize;
+            let polymer = (0..len)
+                .map(|_| units[(rng.next() % used as u64) as usize])
+                .collect::<Vec<_>>();
+
+            let expected = reduce(polymer.iter().copied(), rule);
+            for threads in [2, 3, 4, 7, 16] {
+                assert_eq!(
+                    reduce_parallel(&polymer, threads, rule),
+                    expected,
+                    "{} threads disagree on: {}",
+                    threads,
+                    polymer.iter().collect::<String>(),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn classic_matches_sequential() {
+        let units = ['a', 'A', 'b', 'B', 'c', 'C', 'd', 'D'];
+        assert_matches_sequential(&Classic, &units, 500, 0x2018_0005);
+    }
+
+    #[test]
+    fn unicode_matches_sequential() {
+        // note: `ß` uppercases to "SS", so it never reacts; the Kelvin sign
+        // and titlecase `ǅ` only map one way, so they don't react either
+        let units = ['é', 'É', 'ж', 'Ж', 'k', 'K', '\u{212A}', 'ǅ', 'ǆ', 'Ǆ', 'ß'];
+        assert_matches_sequential(&UnicodeCase, &units, 500, 0x2018_0006);
+    }
+
+    #[test]
+    fn pair_table_matches_sequential() {
+        let buf = std::io::BufReader::new(std::fs::File::open("./input/rules/example.txt").unwrap());
+        let table = PairTable::from_reader(buf).unwrap();
+        let units = ['a', 'Z', '0', '9', '1', '8', 'A', 'z'];
+        assert_matches_sequential(&table, &units, 500, 0x2018_0007);
+    }
+
+    #[test]
+    fn merge_reacts_across_the_boundary() {
+        assert_eq!(merge(vec!['c', 'a', 'b'], vec!['B', 'A', 'd'], &Classic), ['c', 'd']);
+        assert_eq!(merge(vec!['a'], vec!['A'], &Classic), []);
+        assert_eq!(merge(vec!['a'], vec!['a'], &Classic), ['a', 'a']);
+    }
+
+    #[test]
+    fn more_threads_than_units() {
+        assert_eq!(reduce_parallel(&[], 8, &Classic), []);
+        assert_eq!(reduce_parallel(&['a', 'B', 'b'], 8, &Classic), ['a']);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::rules::Rule;
+
+/// A polymer that's kept fully reacted as units are added one at a time.
+///
+/// Every change is recorded, so the most recently added units can be
+/// undone (bringing back anything they reacted with).
+pub struct Polymer<'r, R: Rule + ?Sized> {
+    rule: &'r R,
+    stack: Vec<char>,
+    history: Vec<Change>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    /// The unit was added to the end of the polymer
+    Pushed,
+    /// The unit reacted with (and destroyed) this unit
+    Reacted(char),
+}
+
+impl<'r, R: Rule + ?Sized> Polymer<'r, R> {
+    pub fn new(rule: &'r R) -> Self {
+        Self {
+            rule,
+            stack: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Add a unit to the end of the polymer, letting it react.
+    pub fn push_unit(&mut self, unit: char) {
+        match self.stack.last() {
+            Some(&last) if self.rule.reacts(last, unit) => {
+                self.stack.pop();
+                self.history.push(Change::Reacted(last));
+            },
+            _ => {
+                self.stack.push(unit);
+                self.history.push(Change::Pushed);
+            },
+        }
+    }
+
+    /// Undo the last `push_unit`; returns false if there was nothing to undo.
+    pub fn undo_last(&mut self) -> bool {
+        match self.history.pop() {
+            Some(Change::Pushed) => {
+                self.stack.pop();
+            },
+            Some(Change::Reacted(unit)) => {
+                self.stack.push(unit);
+            },
+            None => return false,
+        }
+
+        true
+    }
+
+    /// Length of the fully reacted polymer.
+    pub fn current_len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Number of units pushed so far (that haven't been undone).
+    pub fn units_pushed(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn units(&self) -> &[char] {
+        &self.stack
+    }
+}
+
+impl<R: Rule + ?Sized> Extend<char> for Polymer<'_, R> {
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        for unit in iter {
+            self.push_unit(unit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reduce::reduce;
+    use crate::rules::Classic;
+
+    #[test]
+    fn undo_brings_back_reacted_units() {
+        let mut polymer = Polymer::new(&Classic);
+        polymer.extend("abB".chars());
+        assert_eq!(polymer.units(), ['a']);
+        assert!(polymer.undo_last());
+        assert_eq!(polymer.units(), ['a', 'b']);
+        polymer.push_unit('B');
+        polymer.push_unit('A');
+        assert_eq!(polymer.units(), []);
+        assert_eq!(polymer.units_pushed(), 4);
+
+        assert!(polymer.undo_last());
+        assert_eq!(polymer.units(), ['a']);
+        assert!(polymer.undo_last() && polymer.undo_last() && polymer.undo_last());
+        assert!(!polymer.undo_last());
+        assert_eq!(polymer.units_pushed(), 0);
+    }
+
+    #[test]
+    fn matches_reduce_at_every_step() {
+        let units = "dabAcCaCBAcCcaDA".chars().collect::<Vec<_>>();
+        let mut polymer = Polymer::new(&Classic);
+        for (i, &unit) in units.iter().enumerate() {
+            polymer.push_unit(unit);
+            assert_eq!(polymer.units(), reduce(units[..=i].iter().copied(), &Classic), "after {} units", i + 1);
+            assert_eq!(polymer.current_len(), polymer.units().len());
+        }
+
+        // and back again
+        for i in (0..units.len()).rev() {
+            assert!(polymer.undo_last());
+            assert_eq!(polymer.units(), reduce(units[..i].iter().copied(), &Classic), "back to {} units", i);
+            assert_eq!(polymer.units_pushed(), i);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::io::BufRead;
+use std::str;
+
+use crate::rules::Rule;
+use crate::Result;
+
+/// Fully react a polymer, returning the remaining units.
+///
+/// The reduced polymer so far is kept as a stack, so each new unit only
+/// needs to be checked against the top of it.
+pub fn reduce<I, R>(it: I, rule: &R) -> Vec<char>
+where I: IntoIterator<Item = char>, R: Rule + ?Sized {
+    let mut stack: Vec<char> = Vec::new();
+    for unit in it {
+        react_onto(&mut stack, unit, rule);
+    }
+
+    stack
+}
+
+/// Same as `reduce`, but reads the polymer straight from `buf` (one buffer at
+/// a time, rather than collecting it first) and skips any whitespace.
+pub fn reduce_reader<B, R>(buf: B, rule: &R) -> Result<Vec<char>>
+where B: BufRead, R: Rule + ?Sized {
+    let mut stack: Vec<char> = Vec::new();
+    for_each_unit(buf, |unit| react_onto(&mut stack, unit, rule))?;
+
+    Ok(stack)
+}
+
+/// Call `f` with every (non-whitespace) unit in `buf`, decoding UTF-8 as we
+/// go; a multi-byte unit split across two buffers is carried over.
+pub fn for_each_unit<B, F>(buf: B, mut f: F) -> Result<()>
+where B: BufRead, F: FnMut(char) {
+    for_each_indexed_unit(buf, |_, unit| f(unit))
+}
+
+/// Same as `for_each_unit`, but also passes along each unit's position in
+/// the input (counted in chars, including any skipped whitespace).
+pub fn for_each_indexed_unit<B, F>(mut buf: B, mut f: F) -> Result<()>
+where B: BufRead, F: FnMut(usize, char) {
+    let mut index = 0;
+    let mut carry: Vec<u8> = Vec::new();
+    loop {
+        let chunk = buf.fill_buf()?;
+        if chunk.is_empty() {
+            break
+        }
+        let len = chunk.len();
+
+        // only copy when there's a partial unit from the last buffer
+        let bytes = if carry.is_empty() {
+            chunk
+        }
+        else {
+            carry.extend_from_slice(chunk);
+            &carry[..]
+        };
+
+        let (valid, rest) = match str::from_utf8(bytes) {
+            Ok(s) => (s, &[][..]),
+            Err(e) if e.error_len().is_none() => {
+                // the buffer ends part-way through a unit
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                (str::from_utf8(valid).unwrap(), rest)
+            },
+            Err(e) => return Err(e.into()),
+        };
+        for unit in valid.chars() {
+            if !unit.is_whitespace() {
+                f(index, unit);
+            }
+            index += 1;
+        }
+
+        let rest = rest.to_vec();
+        buf.consume(len);
+        carry = rest;
+    }
+
+    if !carry.is_empty() {
+        return Err("Polymer ends part-way through a UTF-8 unit".into());
+    }
+
+    Ok(())
+}
+
+fn react_onto<R: Rule + ?Sized>(stack: &mut Vec<char>, unit: char, rule: &R) {
+    match stack.last() {
+        Some(&last) if rule.reacts(last, unit) => {
+            stack.pop();
+        },
+        _ => {
+            stack.push(unit);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::rules::{Classic, UnicodeCase};
+
+    // multi-byte units (2, 3 and 4 bytes), and multi-byte whitespace
+    const POLYMER: &str = "dabAcé\u{3000}ÉCaCBж😀\n Жc\u{3000}CcaDA😀\n";
+
+    #[test]
+    fn units_split_across_buffers() {
+        let expected = POLYMER
+            .chars()
+            .enumerate()
+            .filter(|x| !x.1.is_whitespace())
+            .collect::<Vec<_>>();
+        for capacity in 1..8 {
+            let mut units = Vec::new();
+            let buf = BufReader::with_capacity(capacity, POLYMER.as_bytes());
+            for_each_indexed_unit(buf, |i, unit| units.push((i, unit))).unwrap();
+            assert_eq!(units, expected, "capacity {}", capacity);
+
+            let buf = BufReader::with_capacity(capacity, POLYMER.as_bytes());
+            let units = POLYMER.chars().filter(|x| !x.is_whitespace());
+            assert_eq!(reduce_reader(buf, &UnicodeCase).unwrap(), reduce(units, &UnicodeCase), "capacity {}", capacity);
+        }
+    }
+
+    #[test]
+    fn the_puzzle_example() {
+        let buf = BufReader::with_capacity(3, "dabAcCaCBAcCcaDA\n".as_bytes());
+        assert_eq!(reduce_reader(buf, &Classic).unwrap().iter().collect::<String>(), "dabCBAcaDA");
+    }
+
+    #[test]
+    fn truncated_utf8() {
+        let bytes = &"abé".as_bytes()[..3];
+        for capacity in 1..8 {
+            let err = for_each_unit(BufReader::with_capacity(capacity, bytes), |_| {}).unwrap_err();
+            assert_eq!(err.to_string(), "Polymer ends part-way through a UTF-8 unit", "capacity {}", capacity);
+        }
+
+        // an invalid byte (rather than a missing one) is an error too
+        let bytes = [b'a', 0xff, b'b'];
+        for capacity in 1..8 {
+            assert!(for_each_unit(BufReader::with_capacity(capacity, &bytes[..]), |_| {}).is_err(), "capacity {}", capacity);
+        }
+    }
+}
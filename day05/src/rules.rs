@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::result;
+
+use crate::Result;
+
+/// Decides which pairs of adjacent units react (and destroy each other).
+pub trait Rule: Sync {
+    fn reacts(&self, a: char, b: char) -> bool;
+
+    /// The unit "type" of `unit`; removing a type (for part 2) removes every
+    /// unit that maps to the same type.
+    fn unit_type(&self, unit: char) -> char;
+}
+
+/// The puzzle's rule: same letter, opposite case (ASCII only).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Classic;
+
+/// Same letter, opposite case, for any Unicode letter with a one-to-one
+/// case mapping (e.g. `é` and `É`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnicodeCase;
+
+/// An explicit list of reacting pairs, e.g. loaded from a file.
+///
+/// Each unit can only react with (at most) one other unit; the parallel
+/// reducer relies on this, since otherwise the order that the units react in
+/// changes the result (e.g. with `a b` and `b c`, `abc` could end up as
+/// either `c` or `a`).
+#[derive(Debug, Clone, Default)]
+pub struct PairTable {
+    partners: HashMap<char, char>,
+}
+
+impl Rule for Classic {
+    fn reacts(&self, a: char, b: char) -> bool {
+        a != b && a.eq_ignore_ascii_case(&b)
+    }
+
+    fn unit_type(&self, unit: char) -> char {
+        unit.to_ascii_lowercase()
+    }
+}
+
+impl Rule for UnicodeCase {
+    // note: only pairs whose case mapping round-trips react, which keeps the
+    // rule symmetric with one partner per unit; e.g. the Kelvin sign `K`
+    // lowercases to `k`, but `k` uppercases to `K`, so `k` only reacts with
+    // `K` (and titlecase `ǅ` doesn't react at all)
+    fn reacts(&self, a: char, b: char) -> bool {
+        a != b && (
+            (simple_lowercase(a) == Some(b) && simple_uppercase(b) == Some(a))
+            || (simple_uppercase(a) == Some(b) && simple_lowercase(b) == Some(a))
+        )
+    }
+
+    fn unit_type(&self, unit: char) -> char {
+        match simple_lowercase(unit) {
+            Some(lower) if self.reacts(unit, lower) => lower,
+            _ => unit,
+        }
+    }
+}
+
+// note: `char::to_lowercase` may expand to several chars (e.g. `İ`); those
+// don't have a single opposite-polarity unit, so they never react
+fn simple_lowercase(c: char) -> Option<char> {
+    single(c.to_lowercase())
+}
+
+fn simple_uppercase(c: char) -> Option<char> {
+    single(c.to_uppercase())
+}
+
+fn single<I: Iterator<Item = char>>(mut it: I) -> Option<char> {
+    match (it.next(), it.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+impl PairTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `a` and `b` react with each other (in either order); fails if
+    /// either of them already reacts with some other unit.
+    pub fn insert(&mut self, a: char, b: char) -> Result<()> {
+        for (unit, partner) in [(a, b), (b, a)] {
+            match self.partners.get(&unit) {
+                Some(&x) if x != partner => {
+                    return Err(format!("{:?} already reacts with {:?}", unit, x).into());
+                },
+                _ => {},
+            }
+        }
+
+        self.partners.insert(a, b);
+        self.partners.insert(b, a);
+
+        Ok(())
+    }
+
+    /// Load a pair table, with one pair of units per line separated by
+    /// whitespace (e.g. `a Z`). Blank lines and lines starting with `#` are
+    /// ignored.
+    pub fn from_reader<R: BufRead>(buf: R) -> Result<Self> {
+        let mut table = Self::new();
+        for (i, line) in buf.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+
+            let units = line
+                .split_whitespace()
+                .map(|x| {
+                    let mut chars = x.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Ok(c),
+                        _ => Err(format!("Line {}: expected a single unit, found {:?}", i + 1, x)),
+                    }
+                })
+                .collect::<result::Result<Vec<char>, String>>()?;
+            match units[..] {
+                [a, b] => table.insert(a, b).map_err(|e| format!("Line {}: {}", i + 1, e))?,
+                _ => return Err(format!("Line {}: expected two units, found {}", i + 1, units.len()).into()),
+            }
+        }
+
+        Ok(table)
+    }
+}
+
+impl Rule for PairTable {
+    fn reacts(&self, a: char, b: char) -> bool {
+        self.partners.get(&a) == Some(&b)
+    }
+
+    fn unit_type(&self, unit: char) -> char {
+        // the smaller unit of the pair stands in for both
+        self.partners.get(&unit).map_or(unit, |&x| x.min(unit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pair_table_reacts_both_ways() {
+        let table = PairTable::from_reader("# comment\n\na Z\n0 9\na Z\n".as_bytes()).unwrap();
+        assert!(table.reacts('a', 'Z') && table.reacts('Z', 'a'));
+        assert!(!table.reacts('a', 'A') && !table.reacts('0', '0'));
+        assert_eq!(table.unit_type('a'), 'Z');
+        assert_eq!(table.unit_type('9'), '0');
+        assert_eq!(table.unit_type('x'), 'x');
+    }
+
+    #[test]
+    fn pair_table_rejects_a_second_partner() {
+        let err = PairTable::from_reader("a b\nb c\n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "Line 2: 'b' already reacts with 'a'");
+
+        let err = PairTable::from_reader("a b\n# comment\nc a\n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "Line 3: 'a' already reacts with 'b'");
+    }
+
+    #[test]
+    fn unicode_case_needs_a_round_trip() {
+        let rule = UnicodeCase;
+        assert!(rule.reacts('é', 'É') && rule.reacts('É', 'é'));
+        assert!(rule.reacts('k', 'K') && !rule.reacts('k', '\u{212A}') && !rule.reacts('\u{212A}', 'k'));
+        assert!(rule.reacts('ǆ', 'Ǆ') && !rule.reacts('ǅ', 'ǆ') && !rule.reacts('ǅ', 'Ǆ'));
+        assert_eq!(rule.unit_type('\u{212A}'), '\u{212A}');
+        assert_eq!(rule.unit_type('ǅ'), 'ǅ');
+        assert_eq!(rule.unit_type('Ǆ'), 'ǆ');
+    }
+
+    #[test]
+    fn unicode_case_is_symmetric_with_one_partner() {
+        let rule = UnicodeCase;
+        for a in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            let partners = [simple_lowercase(a), simple_uppercase(a)]
+                .into_iter()
+                .flatten()
+                .filter(|&b| rule.reacts(a, b))
+                .collect::<Vec<_>>();
+            assert!(partners.len() <= 1, "{:?} reacts with {:?}", a, partners);
+            for b in partners {
+                assert!(rule.reacts(b, a), "{:?} reacts with {:?}, but not the other way around", a, b);
+                assert_eq!(rule.unit_type(a), rule.unit_type(b));
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{BufRead, Write};
+
+use serde::Serialize;
+
+use crate::reduce::for_each_indexed_unit;
+use crate::rules::Rule;
+use crate::Result;
+
+/// A unit, along with its position in the original input (in chars).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Unit {
+    pub index: usize,
+    pub unit: char,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reaction {
+    /// 1-based, in the order the reactions happened
+    pub step: usize,
+    pub left: Unit,
+    pub right: Unit,
+}
+
+/// Every reaction that happened while reducing a polymer, plus where each
+/// of the surviving units originally came from.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Trace {
+    pub reactions: Vec<Reaction>,
+    pub survivors: Vec<Unit>,
+}
+
+/// Same as `reduce::reduce_reader`, but keeps track of every unit's original
+/// position so the reactions (and survivors) can be traced back.
+pub fn reduce_traced<B, R>(buf: B, rule: &R) -> Result<Trace>
+where B: BufRead, R: Rule + ?Sized {
+    let mut trace = Trace::default();
+    let mut stack: Vec<Unit> = Vec::new();
+    for_each_indexed_unit(buf, |index, unit| {
+        let right = Unit { index, unit };
+        match stack.last() {
+            Some(&left) if rule.reacts(left.unit, unit) => {
+                stack.pop();
+                trace.reactions.push(Reaction {
+                    step: trace.reactions.len() + 1,
+                    left,
+                    right,
+                });
+            },
+            _ => {
+                stack.push(right);
+            },
+        }
+    })?;
+    trace.survivors = stack;
+
+    Ok(trace)
+}
+
+impl Trace {
+    pub fn write_log<W: Write>(&self, mut out: W) -> Result<()> {
+        for r in &self.reactions {
+            writeln!(
+                out,
+                "step {}: {}@{} + {}@{}",
+                r.step, r.left.unit, r.left.index, r.right.unit, r.right.index,
+            )?;
+        }
+        for (i, s) in self.survivors.iter().enumerate() {
+            writeln!(out, "survivor {}: {}@{}", i, s.unit, s.index)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_json<W: Write>(&self, mut out: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut out, self)?;
+        writeln!(out)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reduce::reduce;
+    use crate::rules::Classic;
+
+    fn unit(index: usize, unit: char) -> Unit {
+        Unit { index, unit }
+    }
+
+    #[test]
+    fn reactions_and_survivors() {
+        // indices count the whitespace too: a0 B1 b3 A4 c6
+        let trace = reduce_traced("aB bA\nc".as_bytes(), &Classic).unwrap();
+        let reactions = trace.reactions
+            .iter()
+            .map(|x| (x.step, x.left, x.right))
+            .collect::<Vec<_>>();
+        assert_eq!(reactions, [(1, unit(1, 'B'), unit(3, 'b')), (2, unit(0, 'a'), unit(4, 'A'))]);
+        assert_eq!(trace.survivors, [unit(6, 'c')]);
+
+        let mut log = Vec::new();
+        trace.write_log(&mut log).unwrap();
+        assert_eq!(String::from_utf8(log).unwrap(), "step 1: B@1 + b@3\nstep 2: a@0 + A@4\nsurvivor 0: c@6\n");
+    }
+
+    #[test]
+    fn survivors_match_reduce() {
+        let polymer = "dabAcCaCBAcCcaDA";
+        let trace = reduce_traced(polymer.as_bytes(), &Classic).unwrap();
+        let survivors = trace.survivors.iter().map(|x| x.unit).collect::<Vec<_>>();
+        assert_eq!(survivors, reduce(polymer.chars(), &Classic));
+        assert_eq!(trace.reactions.len() * 2 + survivors.len(), polymer.len());
+        for s in &trace.survivors {
+            assert_eq!(polymer.chars().nth(s.index), Some(s.unit));
+        }
+    }
+}
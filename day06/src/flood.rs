@@ -0,0 +1,218 @@
+use crate::kdtree::KdTree;
+use crate::metric::Metric;
+use crate::{Point, PointId};
+
+/// Who a cell on the grid belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Owner {
+    Unreached,
+    Point(PointId),
+    /// The cell is equally close to two or more points
+    Tie,
+}
+
+/// The owner of every cell in a rectangle of the grid.
+#[derive(Debug, Clone)]
+pub struct Owners {
+    pub xmin: i32,
+    pub ymin: i32,
+    pub w: i32,
+    pub h: i32,
+    cells: Vec<Owner>,
+}
+
+impl Owners {
+    /// Iterate over every cell as (x, y, owner).
+    pub fn iter(&self) -> impl Iterator<Item = (i32, i32, Owner)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(i, &owner)| {
+                let i = i as i32;
+                (self.xmin + i % self.w, self.ymin + i / self.w, owner)
+            })
+    }
+}
+
+impl Owner {
+    /// Combine the owners of two equally-close paths into a cell.
+    fn merge(self, other: Owner) -> Owner {
+        match (self, other) {
+            (Owner::Unreached, x) | (x, Owner::Unreached) => x,
+            (Owner::Point(a), Owner::Point(b)) if a == b => Owner::Point(a),
+            _ => Owner::Tie,
+        }
+    }
+}
+
+/// Find the closest point to every cell in `xmin..=xmax`, `ymin..=ymax`.
+///
+/// Rather than checking every point for every cell, this floods outwards from
+/// all of the points at once (a multi-source shortest-path search over the
+/// metric's `steps`). Each cell's closest points are exactly the closest
+/// points of the neighbours it's cheapest to reach it from, so a cell is only
+/// owned if all of those neighbours agree; anything else is a tie, same as
+/// `closest_owner`. Each cell is settled once, so this is O(w * h) regardless
+/// of the number of points.
+///
+/// Metrics that can't be flood-filled (Euclidean) fall back to looking up
+/// every cell in a `KdTree`.
+pub fn flood_fill(points: &[Point], metric: Metric, xmin: i32, xmax: i32, ymin: i32, ymax: i32) -> Owners {
+    let w = xmax - xmin + 1;
+    let h = ymax - ymin + 1;
+    let mut owners = Owners {
+        xmin, ymin,
+        w, h,
+        cells: vec![Owner::Unreached; (w * h) as usize],
+    };
+
+    let Some(steps) = metric.steps() else {
+        let tree = KdTree::new(points, metric);
+        for (i, cell) in owners.cells.iter_mut().enumerate() {
+            let (x, y) = (xmin + i as i32 % w, ymin + i as i32 / w);
+            *cell = tree.nearest(x, y);
+        }
+        return owners;
+    };
+
+    // note: this is Dial's algorithm; the costs are small integers, so the
+    // queue is just a ring of buckets, one per distance (modulo the largest
+    // step cost)
+    let max_cost = steps.iter().map(|x| x.2).max().unwrap_or(1);
+    let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); max_cost as usize + 1];
+    let mut dist = vec![u64::MAX; (w * h) as usize];
+    let mut pending = 0usize;
+
+    for point in points {
+        if point.x < xmin || point.x > xmax || point.y < ymin || point.y > ymax {
+            continue
+        }
+
+        let idx = ((point.x - xmin) + w * (point.y - ymin)) as usize;
+        if dist[idx] == u64::MAX {
+            buckets[0].push(idx);
+            pending += 1;
+        }
+        dist[idx] = 0;
+        owners.cells[idx] = owners.cells[idx].merge(Owner::Point(point.id));
+    }
+
+    let mut d = 0u64;
+    while pending > 0 {
+        let bucket = std::mem::take(&mut buckets[(d % (max_cost + 1)) as usize]);
+        pending -= bucket.len();
+        for idx in bucket {
+            if dist[idx] != d {
+                // we've since found a cheaper way to this cell
+                continue
+            }
+
+            let (x, y) = ((idx as i32) % w, (idx as i32) / w);
+            let owner = owners.cells[idx];
+            for &(dx, dy, cost) in &steps {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= w || ny >= h {
+                    continue
+                }
+
+                let nidx = (nx + w * ny) as usize;
+                let nd = d + cost;
+                if nd < dist[nidx] {
+                    dist[nidx] = nd;
+                    owners.cells[nidx] = owner;
+                    buckets[(nd % (max_cost + 1)) as usize].push(nidx);
+                    pending += 1;
+                }
+                else if nd == dist[nidx] {
+                    owners.cells[nidx] = owners.cells[nidx].merge(owner);
+                }
+            }
+        }
+
+        d += 1;
+    }
+
+    owners
+}
+
+/// Check every point to find the owner of a single cell.
+pub fn closest_owner(points: &[Point], metric: Metric, x: i32, y: i32) -> Owner {
+    let mut best = (u64::MAX, Owner::Unreached);
+    for point in points {
+        let d = metric.key(x, y, point.x, point.y);
+        if d < best.0 {
+            best = (d, Owner::Point(point.id));
+        }
+        else if d == best.0 {
+            best = (d, Owner::Tie);
+        }
+    }
+
+    best.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random_points;
+
+    const METRICS: [Metric; 5] = [
+        Metric::Manhattan,
+        Metric::Chebyshev,
+        Metric::Euclidean,
+        Metric::Weighted { wx: 3, wy: 1 },
+        Metric::Weighted { wx: 2, wy: 5 },
+    ];
+
+    // flood the bounding box plus a `margin` around it, and return how many
+    // of the cells were ties
+    fn assert_matches_brute_force(points: &[Point], metric: Metric, margin: i32) -> usize {
+        let xmin = points.iter().map(|p| p.x).min().unwrap() - margin;
+        let xmax = points.iter().map(|p| p.x).max().unwrap() + margin;
+        let ymin = points.iter().map(|p| p.y).min().unwrap() - margin;
+        let ymax = points.iter().map(|p| p.y).max().unwrap() + margin;
+
+        let owners = flood_fill(points, metric, xmin, xmax, ymin, ymax);
+        assert_eq!((owners.w, owners.h), (xmax - xmin + 1, ymax - ymin + 1));
+        let mut ties = 0;
+        for (x, y, owner) in owners.iter() {
+            assert_eq!(owner, closest_owner(points, metric, x, y), "{} at ({}, {})", metric, x, y);
+            ties += (owner == Owner::Tie) as usize;
+        }
+
+        ties
+    }
+
+    #[test]
+    fn random_points_for_every_metric() {
+        for metric in METRICS {
+            let mut ties = 0;
+            for seed in 1..=5 {
+                // crowded enough that there are ties (and duplicate points)
+                ties += assert_matches_brute_force(&random_points(40, 20, seed), metric, 4);
+            }
+            assert!(ties > 0, "{} never tied", metric);
+        }
+    }
+
+    #[test]
+    fn ties() {
+        let points = [(0, 0), (2, 0), (6, 6), (6, 6), (0, 8)]
+            .iter()
+            .enumerate()
+            .map(|(id, &(x, y))| Point { id, x, y })
+            .collect::<Vec<_>>();
+        for metric in METRICS {
+            assert_matches_brute_force(&points, metric, 3);
+        }
+
+        let owners = flood_fill(&points, Metric::Manhattan, -3, 9, -3, 11);
+        let at = |x: i32, y: i32| owners.iter().find(|c| (c.0, c.1) == (x, y)).unwrap().2;
+        assert_eq!(at(1, 0), Owner::Tie);
+        assert_eq!(at(0, 4), Owner::Tie);
+        assert_eq!(at(0, 0), Owner::Point(0));
+        // both points at (6, 6) tie everywhere, including on the point itself
+        assert_eq!(at(6, 6), Owner::Tie);
+        assert_eq!(at(9, 9), Owner::Tie);
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::BinaryHeap;
+
+use crate::flood::Owner;
+use crate::metric::Metric;
+use crate::Point;
+
+/// A 2-d tree over a set of points, for finding the closest point(s) to a
+/// cell without checking every point.
+///
+/// This is for looking up a few cells at a time (e.g. `--owner`), and for
+/// `flood::flood_fill` with metrics that can't be flooded. Finding *every*
+/// region is left to the flood fill itself: it settles each cell once no
+/// matter how many points there are, where the tree still costs a search per
+/// cell (see `--bench`).
+#[derive(Debug)]
+pub struct KdTree<'a> {
+    points: &'a [Point],
+    metric: Metric,
+    // indices into `points`, arranged so that the middle of every slice
+    // splits the rest of it: on x at even depths, on y at odd depths
+    order: Vec<usize>,
+}
+
+// what a search is looking for; the search skips any part of the tree
+// that's further away than `limit`
+trait Collector {
+    fn add(&mut self, key: u64, idx: usize);
+    fn limit(&self) -> u64;
+}
+
+struct Nearest {
+    key: u64,
+    // the index of the closest point, or `None` while it's a tie
+    idx: Option<usize>,
+}
+
+struct KNearest {
+    k: usize,
+    // the closest `k` so far, as (key, index), with the furthest on top
+    heap: BinaryHeap<(u64, usize)>,
+}
+
+impl<'a> KdTree<'a> {
+    pub fn new(points: &'a [Point], metric: Metric) -> Self {
+        let mut order = (0..points.len()).collect::<Vec<_>>();
+        build(points, &mut order, 0);
+
+        KdTree { points, metric, order }
+    }
+
+    /// The owner of cell (x, y): the closest point, or a tie if two or more
+    /// points are equally close.
+    pub fn nearest(&self, x: i32, y: i32) -> Owner {
+        let mut nearest = Nearest { key: u64::MAX, idx: None };
+        self.search(&self.order, 0, x, y, &mut nearest);
+
+        match (nearest.key, nearest.idx) {
+            (u64::MAX, _) => Owner::Unreached,
+            (_, Some(idx)) => Owner::Point(self.points[idx].id),
+            (_, None) => Owner::Tie,
+        }
+    }
+
+    /// The `k` closest points to cell (x, y), closest first; points that are
+    /// equally close are ordered by their position in the input.
+    pub fn k_nearest(&self, x: i32, y: i32, k: usize) -> Vec<&'a Point> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let mut nearest = KNearest { k, heap: BinaryHeap::with_capacity(k + 1) };
+        self.search(&self.order, 0, x, y, &mut nearest);
+
+        nearest.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(_, idx)| &self.points[idx])
+            .collect()
+    }
+
+    fn search<C: Collector>(&self, order: &[usize], depth: usize, x: i32, y: i32, collector: &mut C) {
+        if order.is_empty() {
+            return
+        }
+
+        let mid = order.len() / 2;
+        let point = &self.points[order[mid]];
+        collector.add(self.metric.key(x, y, point.x, point.y), order[mid]);
+
+        // search the side the cell is on first, then the other side only if
+        // it could still be close enough; a point on the other side is at
+        // least as far away as the splitting line
+        let diff = coord(x, y, depth) - coord(point.x, point.y, depth);
+        let (near, far) = match diff < 0 {
+            true => (&order[..mid], &order[mid + 1..]),
+            false => (&order[mid + 1..], &order[..mid]),
+        };
+        self.search(near, depth + 1, x, y, collector);
+        let bound = match depth & 1 {
+            0 => self.metric.key(diff, 0, 0, 0),
+            _ => self.metric.key(0, diff, 0, 0),
+        };
+        if bound <= collector.limit() {
+            self.search(far, depth + 1, x, y, collector);
+        }
+    }
+}
+
+fn coord(x: i32, y: i32, depth: usize) -> i32 {
+    if depth & 1 == 0 { x } else { y }
+}
+
+fn build(points: &[Point], order: &mut [usize], depth: usize) {
+    if order.len() <= 1 {
+        return
+    }
+
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by_key(mid, |&i| coord(points[i].x, points[i].y, depth));
+    let (left, right) = order.split_at_mut(mid);
+    build(points, left, depth + 1);
+    build(points, &mut right[1..], depth + 1);
+}
+
+impl Collector for Nearest {
+    fn add(&mut self, key: u64, idx: usize) {
+        if key < self.key {
+            self.key = key;
+            self.idx = Some(idx);
+        }
+        else if key == self.key {
+            self.idx = None;
+        }
+    }
+
+    fn limit(&self) -> u64 {
+        self.key
+    }
+}
+
+impl Collector for KNearest {
+    fn add(&mut self, key: u64, idx: usize) {
+        self.heap.push((key, idx));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    fn limit(&self) -> u64 {
+        match self.heap.len() < self.k {
+            true => u64::MAX,
+            false => self.heap.peek().map(|x| x.0).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flood::closest_owner;
+    use crate::random_points;
+
+    fn assert_matches_brute_force(metric: Metric) {
+        let points = random_points(300, 60, 0x2018_0006);
+        let tree = KdTree::new(&points, metric);
+        for y in -5..65 {
+            for x in -5..65 {
+                assert_eq!(tree.nearest(x, y), closest_owner(&points, metric, x, y), "{} at ({}, {})", metric, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_with_asymmetric_weights() {
+        for (wx, wy) in [(5, 1), (1, 5), (3, 7)] {
+            assert_matches_brute_force(Metric::Weighted { wx, wy });
+        }
+    }
+
+    #[test]
+    fn nearest_with_symmetric_metrics() {
+        for metric in [Metric::Manhattan, Metric::Chebyshev, Metric::Euclidean] {
+            assert_matches_brute_force(metric);
+        }
+    }
+
+    #[test]
+    fn k_nearest_matches_sorted_distances() {
+        let points = random_points(200, 40, 0x2018_0007);
+        for metric in [Metric::Manhattan, Metric::Weighted { wx: 5, wy: 1 }, Metric::Euclidean] {
+            let tree = KdTree::new(&points, metric);
+            for (x, y) in [(0, 0), (20, 20), (-10, 50), (39, 3)] {
+                let mut expected = points.iter().collect::<Vec<_>>();
+                expected.sort_by_key(|p| (metric.key(x, y, p.x, p.y), p.id));
+                expected.truncate(7);
+
+                let found = tree.k_nearest(x, y, 7).iter().map(|p| p.id).collect::<Vec<_>>();
+                assert_eq!(found, expected.iter().map(|p| p.id).collect::<Vec<_>>(), "{} at ({}, {})", metric, x, y);
+            }
+        }
+    }
+}
@@ -1,15 +1,27 @@
+use std::collections::HashMap;
 use std::error;
 use std::fs;
 use std::io::{self, BufRead};
 use std::result;
-use std::collections::{HashMap, HashSet};
-use std::cmp;
+use std::env;
+use std::time::Instant;
+
+mod flood;
+mod kdtree;
+mod metric;
+mod nd;
+mod regions;
+mod render;
+
+use flood::Owner;
+use kdtree::KdTree;
+use metric::Metric;
 
 const INPUT_FILE: &str = "./input/coordinates.txt";
+const THRESHOLD: u32 = 10_000;
 
 type Result<T> = result::Result<T, Box<dyn error::Error>>;
 type PointId = usize;
-type GridEntry<'a> = (u32, Option<&'a Point>);
 
 #[derive(Debug)]
 struct Grid {
@@ -27,157 +39,489 @@ struct Point {
     x: i32,
     y: i32,
     id: PointId,
-    size: u32,
-    infinite: bool,
+}
+
+/// What to do with a point at the same position as an earlier one; the two
+/// would tie everywhere, so neither could ever own any cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum DuplicatePolicy {
+    #[default]
+    Error,
+    Skip,
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    input: Option<String>,
+    duplicates: DuplicatePolicy,
+    metric: Metric,
+    threshold: Option<u32>,
+    render: Option<String>,
+    report: bool,
+    owner: Option<(i32, i32)>,
+    nearest: Option<usize>,
+    // (number of points, grid size)
+    bench: Option<(usize, i32)>,
 }
 
 fn main() -> Result<()> {
-    let buf = io::BufReader::new(fs::File::open(&INPUT_FILE)?);
-    let mut grid = parse_grid(buf)?;
+    let opts = parse_args(env::args().skip(1))?;
+    if let Some((n, size)) = opts.bench {
+        return bench(n, size, opts.metric);
+    }
 
-    let p1 = part1(&mut grid)?;
-    let p2 = part2(&mut grid)?;
+    let input = opts.input.as_deref().unwrap_or(INPUT_FILE);
+    let buf = io::BufReader::new(fs::File::open(input)?);
+    let coords = parse_coords(buf, opts.duplicates)?;
+    if coords.first().is_some_and(|x| x.len() != 2) {
+        return solve_nd(coords, &opts);
+    }
+    let grid = Grid::from_coords(coords)?;
+
+    if let Some((x, y)) = opts.owner {
+        return lookup(&grid, opts.metric, x, y, opts.nearest);
+    }
+
+    let threshold = opts.threshold.unwrap_or(THRESHOLD);
+    let regions = regions::regions(&grid, opts.metric);
+    let p1 = part1(&regions)?;
+    let p2 = part2(&grid, opts.metric, threshold)?;
 
     println!("Part 1: {}", p1);
     println!("Part 2: {}", p2);
 
+    if opts.report {
+        let stdout = io::stdout();
+        regions::write_report(stdout.lock(), &grid, &regions)?;
+    }
+
+    if let Some(path) = &opts.render {
+        let safe = safe_region(&grid, opts.metric, threshold)?;
+        let out = io::BufWriter::new(fs::File::create(path)?);
+        render::write_regions(out, &grid, opts.metric, &regions, &safe)?;
+        println!("Regions written to: {}", path);
+    }
+
     Ok(())
 }
 
-fn parse_grid<B: BufRead>(buf: B) -> Result<Grid> {
-    // convert the "x,y" lines into Points objects
-    let points = buf
-        .lines()
-        .flatten()
-        .enumerate()
-        .map(|(id, x)| parse_line(id, &x))
-        .collect::<Result<Vec<_>>>()?;
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options> {
+    let mut opts = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => {
+                opts.input = Some(args.next().ok_or("--input requires a path")?);
+            },
+            "--duplicates" => {
+                opts.duplicates = match args.next().as_deref() {
+                    Some("error") => DuplicatePolicy::Error,
+                    Some("skip") => DuplicatePolicy::Skip,
+                    _ => return Err("--duplicates should be one of: error, skip".into()),
+                };
+            },
+            "--threshold" => {
+                opts.threshold = Some(args.next().ok_or("--threshold requires a number")?.parse()?);
+            },
+            "--report" => opts.report = true,
+            "--owner" => {
+                let cell = args.next().ok_or("--owner requires a cell (x,y)")?;
+                let (x, y) = cell.split_once(',').ok_or("--owner should look like: --owner X,Y")?;
+                opts.owner = Some((x.trim().parse()?, y.trim().parse()?));
+            },
+            "--nearest" => {
+                opts.nearest = Some(args.next().ok_or("--nearest requires a number of points")?.parse()?);
+            },
+            "--render" => {
+                opts.render = Some(args.next().ok_or("--render requires an output path")?);
+            },
+            "--metric" => {
+                opts.metric = args.next().ok_or("--metric requires a metric")?.parse()?;
+            },
+            "--bench" => {
+                let n = args.next().ok_or("--bench requires a number of points")?.parse()?;
+                let size = args.next().map(|x| x.parse()).transpose()?.unwrap_or(400);
+                opts.bench = Some((n, size));
+            },
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
 
-    if points.len() < 1 {
-        return Err("Could not find any valid coordinates in the input".into());
+    if opts.nearest.is_some() && opts.owner.is_none() {
+        return Err("--nearest can only be used together with --owner".into());
     }
 
-    let xmin = points.iter().map(|p| p.x).min().unwrap();
-    let xmax = points.iter().map(|p| p.x).max().unwrap();
-    let ymin = points.iter().map(|p| p.y).min().unwrap();
-    let ymax = points.iter().map(|p| p.y).max().unwrap();
-    let w = xmax - xmin + 1;
-    let h = ymax - ymin + 1;
+    Ok(opts)
+}
 
-    let mut grid = Grid {
-        points,
-        xmin, xmax,
-        ymin, ymax,
-        w, h,
-    };
+fn parse_coords<B: BufRead>(buf: B, duplicates: DuplicatePolicy) -> Result<Vec<Vec<i32>>> {
+    // convert the "x,y" (or "x,y,z", ...) lines into lists of coordinates,
+    // skipping blank lines and comments
+    let mut coords: Vec<Vec<i32>> = Vec::new();
+    let mut seen = HashMap::new();
+    for (i, line) in buf.lines().enumerate() {
+        let line = line?;
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue
+        }
+
+        let point = parse_line(line).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        if let Some(first) = coords.first() {
+            if point.len() != first.len() {
+                return Err(format!("Line {}: expected {} coordinates, found {}", i + 1, first.len(), point.len()).into());
+            }
+        }
 
-    // if any point is closest to the border *around* our grid, then that
-    // point is infinite
-    let mut infinite_points: HashSet<PointId> = HashSet::new();
-    for x in xmin-1..=xmax+1 {
-        for y in ymin-1..=ymax+1 {
-            if x > xmin && y > ymin && x < xmax && y < ymax { continue }
-            if let Some(point) = closest_point(&grid, x, y).1 {
-                infinite_points.insert(point.id);
+        if let Some(other) = seen.get(&point) {
+            let duplicate = format!("({}) is a duplicate of line {}", join(&point), other);
+            match duplicates {
+                DuplicatePolicy::Error => return Err(format!("Line {}: {}", i + 1, duplicate).into()),
+                DuplicatePolicy::Skip => {
+                    eprintln!("Warning: skipping line {}: {}", i + 1, duplicate);
+                    continue
+                },
             }
         }
+
+        seen.insert(point.clone(), i + 1);
+        coords.push(point);
     }
-    for point in grid.points.iter_mut() {
-        if infinite_points.contains(&point.id) {
-            point.infinite = true;
+
+    Ok(coords)
+}
+
+fn join(coords: &[i32]) -> String {
+    coords
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Grid {
+    fn from_coords(coords: Vec<Vec<i32>>) -> Result<Self> {
+        let points = coords
+            .into_iter()
+            .enumerate()
+            .map(|(id, c)| match c[..] {
+                [x, y] => Ok(Point { id, x, y }),
+                _ => Err(format!("Expected 2 coordinates, found {}", c.len())),
+            })
+            .collect::<result::Result<Vec<_>, _>>()?;
+
+        Grid::new(points)
+    }
+
+    fn new(points: Vec<Point>) -> Result<Self> {
+        if points.is_empty() {
+            return Err("Could not find any valid coordinates in the input".into());
         }
+
+        let xmin = points.iter().map(|p| p.x).min().unwrap();
+        let xmax = points.iter().map(|p| p.x).max().unwrap();
+        let ymin = points.iter().map(|p| p.y).min().unwrap();
+        let ymax = points.iter().map(|p| p.y).max().unwrap();
+        let w = xmax - xmin + 1;
+        let h = ymax - ymin + 1;
+
+        Ok(Grid {
+            points,
+            xmin, xmax,
+            ymin, ymax,
+            w, h,
+        })
     }
+}
 
-    Ok(grid)
+fn parse_line(line: &str) -> result::Result<Vec<i32>, String> {
+    line.split(',')
+        .map(|x| {
+            let x = x.trim();
+            x.parse().map_err(|e| format!("invalid coordinate {:?}: {}", x, e))
+        })
+        .collect()
 }
 
-fn parse_line(id: PointId, line: &str) -> Result<Point> {
-    let parts: Vec<&str> = line.split(",").collect();
-    let x: i32 = parts[0].trim().parse()?;
-    let y: i32 = parts[1].trim().parse()?;
+/// Solve both parts for points that aren't 2-D; only Manhattan distance is
+/// supported, and none of the grid-based extras are.
+fn solve_nd(coords: Vec<Vec<i32>>, opts: &Options) -> Result<()> {
+    if opts.metric != Metric::Manhattan {
+        return Err(format!("Metric {} is only supported for 2-D coordinates", opts.metric).into());
+    }
+    if opts.report || opts.render.is_some() || opts.owner.is_some() {
+        return Err("--report, --render and --owner are only supported for 2-D coordinates".into());
+    }
 
-    Ok(Point {
-        id,
-        x,
-        y,
-        size: 0,
-        infinite: false,
-    })
+    let space = nd::Space::new(coords)?;
+    let p1 = space.largest_region()?;
+    let p2 = space.safe_area(opts.threshold.unwrap_or(THRESHOLD))?;
+
+    println!("Dimensions: {}", space.dims());
+    println!("Part 1: {}", p1);
+    println!("Part 2: {}", p2);
+
+    Ok(())
 }
 
-fn dist(ax: i32, ay: i32, bx: i32, by: i32) -> u32 {
-    let dx = if ax > bx { ax - bx } else { bx - ax };
-    let dy = if ay > by { ay - by } else { by - ay };
+fn part1(regions: &[regions::Region]) -> Result<u64> {
+    // the largest region that isn't infinite
+    let largest = regions
+        .iter()
+        .filter_map(|x| x.area)
+        .max()
+        .unwrap_or(0);
 
-    (dx + dy) as u32
+    Ok(largest)
 }
 
-fn closest_point(grid: &Grid, x: i32, y: i32) -> GridEntry {
-    let mut entry: GridEntry = (u32::MAX, None);
-    for point in &grid.points {
-        let d = dist(x, y, point.x, point.y);
-        if d < entry.0 {
-            entry = (d, Some(point));
-        }
-        else if d == entry.0 {
-            entry = (d, None);
-        }
+/// Print out who owns cell (x, y), and optionally the `k` closest points to it.
+fn lookup(grid: &Grid, metric: Metric, x: i32, y: i32, k: Option<usize>) -> Result<()> {
+    let tree = KdTree::new(&grid.points, metric);
+    match tree.nearest(x, y) {
+        Owner::Point(id) => {
+            let point = &grid.points[id];
+            println!("Owner of ({}, {}): point {} ({}, {})", x, y, id, point.x, point.y);
+        },
+        _ => println!("Owner of ({}, {}): tie", x, y),
     }
 
-    entry
+    for point in tree.k_nearest(x, y, k.unwrap_or(0)) {
+        println!("  {:<5}  ({}, {})  {}", point.id, point.x, point.y, metric.distance(x, y, point.x, point.y));
+    }
+
+    Ok(())
 }
 
-fn part1(grid: &mut Grid) -> Result<u32> {
-    // find the closes 'point' for each position on the grid
-    let mut point_owners: Vec<GridEntry> = vec![(u32::MAX, None); (grid.w * grid.h) as usize];
-    for y in grid.ymin..=grid.ymax {
-        for x in grid.xmin..=grid.xmax {
-            let idx = ((x - grid.xmin) + grid.w * (y - grid.ymin)) as usize;
-            point_owners[idx] = closest_point(grid, x, y);
-        }
+/// The cells whose total distance to every point is below a threshold.
+#[derive(Debug)]
+struct SafeRegion {
+    xmin: i32,
+    ymin: i32,
+    w: i32,
+    h: i32,
+    cells: Vec<bool>,
+}
+
+impl SafeRegion {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        let (dx, dy) = (x - self.xmin, y - self.ymin);
+        dx >= 0 && dy >= 0 && dx < self.w && dy < self.h && self.cells[dx as usize + self.w as usize * dy as usize]
     }
 
-    // map grid_id -> size
-    let mut size_by_point = HashMap::new();
-    point_owners
-        .iter()
-        .map(|x| x.1)
-        .flatten()
-        .for_each(|x| {
-            let entry = size_by_point.entry(x.id).or_insert(0u32);
-            *entry += 1;
-        });
-    
-    // update all points with their computed size
-    // for point in grid.points.iter_mut() {
-    for point in grid.points.iter_mut().filter(|x| !x.infinite) {
-        if let Some(size) = size_by_point.get(&point.id) {
-            point.size = *size;
-        }
+    fn area(&self) -> u64 {
+        self.cells.iter().filter(|&&x| x).count() as u64
     }
+}
 
-    // sort by size, desc
-    grid.points.sort_by_key(|x| cmp::Reverse(x.size));
-    Ok(grid.points[0].size)
+fn part2(grid: &Grid, metric: Metric, threshold: u32) -> Result<u64> {
+    Ok(safe_region(grid, metric, threshold)?.area())
 }
 
-fn part2(grid: &mut Grid) -> Result<u32> {
-    let mut point_distances = vec![0; (grid.w * grid.h) as usize];
-    for y in grid.ymin..=grid.ymax {
-        for x in grid.xmin..=grid.xmax {
-            let idx = ((x - grid.xmin) + grid.w * (y - grid.ymin)) as usize;
-            let total_distance: u32 = grid.points
+fn safe_region(grid: &Grid, metric: Metric, threshold: u32) -> Result<SafeRegion> {
+    // the safe region isn't limited to the bounding box: a cell `d` steps
+    // outside of it is at least `d * unit` from every point, so its total
+    // distance is at least `n * d * unit`, and anything further out than
+    // `threshold / (n * unit)` can't possibly be safe
+    let n = grid.points.len() as u64;
+    let margin = (threshold as u64).div_ceil(n * metric.unit() as u64);
+    let too_large = || format!("Threshold is too large: {}", threshold);
+    let margin = i32::try_from(margin).map_err(|_| too_large())?;
+
+    // the bounding box grown by `margin` on every side, which still has to
+    // fit in an i32 at both ends
+    let grow = |min: i32, len: i32| {
+        let len = margin.checked_mul(2).and_then(|x| len.checked_add(x))?;
+        let min = min.checked_sub(margin)?;
+        min.checked_add(len).map(|_| (min, len))
+    };
+    let (xmin, w) = grow(grid.xmin, grid.w).ok_or_else(too_large)?;
+    let (ymin, h) = grow(grid.ymin, grid.h).ok_or_else(too_large)?;
+    // same limit as `nd::Space`; past that the search would never finish
+    // anyway
+    if w as u64 * h as u64 > u32::MAX as u64 {
+        return Err(format!("Safe region is too large to search ({}x{} cells) at threshold {}", w, h, threshold).into());
+    }
+
+    let mut region = SafeRegion { xmin, ymin, w, h, cells: Vec::new() };
+
+    // note: the distances are only fractional for Euclidean; everything
+    // else sums exactly
+    for y in region.ymin..(region.ymin + region.h) {
+        for x in region.xmin..(region.xmin + region.w) {
+            let total_distance: f64 = grid.points
                 .iter()
-                .map(|p| dist(x, y, p.x, p.y))
+                .map(|p| metric.distance(x, y, p.x, p.y))
                 .sum();
-            point_distances[idx] = total_distance;
+            region.cells.push(total_distance < threshold as f64);
         }
     }
 
-    let area = point_distances
+    Ok(region)
+}
+
+/// `n` repeatable random points in a `size` x `size` square (for the benchmark
+/// and the tests), seeded by `seed`.
+fn random_points(n: usize, size: i32, seed: u64) -> Vec<Point> {
+    // tiny xorshift PRNG, so the points are repeatable without any extra
+    // dependencies
+    let mut state = seed;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    (0..n)
+        .map(|id| Point {
+            id,
+            x: (next() % size as u64) as i32,
+            y: (next() % size as u64) as i32,
+        })
+        .collect()
+}
+
+/// Time `flood::closest_owner` against `flood::flood_fill` and `KdTree` on `n`
+/// random points in a `size` x `size` square, and make sure they all agree on
+/// every cell.
+fn bench(n: usize, size: i32, metric: Metric) -> Result<()> {
+    let points = random_points(n, size, 0x2018_0006);
+    let grid = Grid::new(points)?;
+
+    let start = Instant::now();
+    let owners = flood::flood_fill(&grid.points, metric, grid.xmin, grid.xmax, grid.ymin, grid.ymax);
+    let flood_time = start.elapsed();
+
+    let start = Instant::now();
+    let tree = KdTree::new(&grid.points, metric);
+    let nearest = owners
+        .iter()
+        .map(|(x, y, _)| tree.nearest(x, y))
+        .collect::<Vec<_>>();
+    let tree_time = start.elapsed();
+
+    let start = Instant::now();
+    let brute = owners
         .iter()
-        .filter(|&x| *x < 10_000)
-        .count();
-    
-    Ok(area as u32)
+        .map(|(x, y, _)| flood::closest_owner(&grid.points, metric, x, y))
+        .collect::<Vec<_>>();
+    let brute_time = start.elapsed();
+
+    let mut flood_mismatches = 0;
+    let mut tree_mismatches = 0;
+    for (((_, _, owner), nearest), brute) in owners.iter().zip(nearest).zip(brute) {
+        flood_mismatches += (owner != brute) as u32;
+        tree_mismatches += (nearest != brute) as u32;
+    }
+
+    println!("Metric:      {}", metric);
+    println!("Points:      {}", n);
+    println!("Grid:        {}x{}", grid.w, grid.h);
+    println!("Brute force: {:?}", brute_time);
+    println!("Flood fill:  {:?} ({:.1}x)", flood_time, brute_time.as_secs_f64() / flood_time.as_secs_f64());
+    println!("K-d tree:    {:?} ({:.1}x)", tree_time, brute_time.as_secs_f64() / tree_time.as_secs_f64());
+    if flood_mismatches > 0 {
+        return Err(format!("Flood fill disagreed with brute force on {} cells", flood_mismatches).into());
+    }
+    if tree_mismatches > 0 {
+        return Err(format!("K-d tree disagreed with brute force on {} cells", tree_mismatches).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(path: &str, duplicates: DuplicatePolicy) -> Result<Vec<Vec<i32>>> {
+        parse_coords(io::BufReader::new(fs::File::open(path)?), duplicates)
+    }
+
+    fn parse_error(name: &str) -> String {
+        parse(&format!("./input/malformed/{}", name), DuplicatePolicy::Error).unwrap_err().to_string()
+    }
+
+    fn load(path: &str) -> Grid {
+        Grid::from_coords(parse(path, DuplicatePolicy::Error).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let coords = parse("./input/malformed/comments.txt", DuplicatePolicy::Error).unwrap();
+        let expected = parse("./input/spill/example.txt", DuplicatePolicy::Error).unwrap();
+        assert_eq!(coords, expected);
+        assert_eq!(coords, [[1, 1], [1, 6], [8, 3], [3, 4], [5, 5], [8, 9]]);
+    }
+
+    #[test]
+    fn duplicates_are_rejected_by_default() {
+        assert_eq!(parse_error("duplicate.txt"), "Line 4: (1, 6) is a duplicate of line 2");
+    }
+
+    #[test]
+    fn duplicates_can_be_skipped() {
+        let coords = parse("./input/malformed/duplicate.txt", DuplicatePolicy::Skip).unwrap();
+        assert_eq!(coords, [[1, 1], [1, 6], [8, 3], [3, 4]]);
+    }
+
+    #[test]
+    fn malformed_lines_name_the_line() {
+        assert!(parse_error("empty_field.txt").starts_with("Line 2: invalid coordinate \"\""));
+        assert_eq!(parse_error("extra_field.txt"), "Line 2: expected 2 coordinates, found 3");
+        assert!(parse_error("missing_comma.txt").starts_with("Line 2: invalid coordinate \"1 6\""));
+        assert!(parse_error("not_a_number.txt").starts_with("Line 2: invalid coordinate \"x\""));
+    }
+
+    #[test]
+    fn no_coordinates_at_all() {
+        let coords = parse("./input/malformed/empty.txt", DuplicatePolicy::Error).unwrap();
+        assert!(coords.is_empty());
+        let err = Grid::from_coords(coords).unwrap_err();
+        assert_eq!(err.to_string(), "Could not find any valid coordinates in the input");
+    }
+
+    fn spill(name: &str, metric: Metric, threshold: u32) -> u64 {
+        part2(&load(&format!("./input/spill/{}", name)), metric, threshold).unwrap()
+    }
+
+    #[test]
+    fn safe_region_spills_past_the_bounding_box() {
+        assert_eq!(spill("single.txt", Metric::Manhattan, 10), 181);
+        assert_eq!(spill("pair.txt", Metric::Manhattan, 10), 33);
+        assert_eq!(spill("diamond.txt", Metric::Manhattan, 10), 1);
+        assert_eq!(spill("example.txt", Metric::Manhattan, 10), 0);
+    }
+
+    #[test]
+    fn safe_region_at_a_larger_threshold() {
+        assert_eq!(spill("single.txt", Metric::Manhattan, 32), 1985);
+        assert_eq!(spill("pair.txt", Metric::Manhattan, 32), 473);
+        assert_eq!(spill("diamond.txt", Metric::Manhattan, 32), 109);
+        assert_eq!(spill("example.txt", Metric::Manhattan, 32), 16);
+    }
+
+    #[test]
+    fn safe_region_with_other_metrics() {
+        assert_eq!(spill("pair.txt", Metric::Chebyshev, 32), 957);
+        assert_eq!(spill("pair.txt", Metric::Euclidean, 32), 793);
+        assert_eq!(spill("pair.txt", Metric::Weighted { wx: 2, wy: 3 }, 32), 73);
+    }
+
+    #[test]
+    fn threshold_too_large_for_the_margin() {
+        let grid = load("./input/spill/single.txt");
+        let err = part2(&grid, Metric::Manhattan, 2_000_000_000).unwrap_err();
+        assert_eq!(err.to_string(), "Threshold is too large: 2000000000");
+    }
+
+    #[test]
+    fn threshold_too_large_to_search() {
+        let grid = load("./input/spill/single.txt");
+        let err = part2(&grid, Metric::Manhattan, 100_000_000).unwrap_err();
+        assert_eq!(err.to_string(), "Safe region is too large to search (200000001x200000001 cells) at threshold 100000000");
+
+        // a single point's safe region is a diamond of 2t^2 - 2t + 1 cells
+        assert_eq!(part2(&grid, Metric::Manhattan, 1_000).unwrap(), 2 * 1_000 * 1_000 - 2 * 1_000 + 1);
+    }
 }
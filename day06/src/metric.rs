@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Result;
+
+/// How distance between two cells is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Metric {
+    /// |dx| + |dy|
+    #[default]
+    Manhattan,
+    /// max(|dx|, |dy|)
+    Chebyshev,
+    /// sqrt(dx^2 + dy^2)
+    Euclidean,
+    /// wx * |dx| + wy * |dy|
+    Weighted { wx: u32, wy: u32 },
+}
+
+impl Metric {
+    /// A value that orders cells the same way the true distance does, but is
+    /// always an exact integer (for Euclidean, this is the squared distance).
+    /// Use this for any "which is closer?" comparisons.
+    pub fn key(&self, ax: i32, ay: i32, bx: i32, by: i32) -> u64 {
+        let dx = (ax - bx).unsigned_abs() as u64;
+        let dy = (ay - by).unsigned_abs() as u64;
+        match *self {
+            Metric::Manhattan => dx + dy,
+            Metric::Chebyshev => dx.max(dy),
+            Metric::Euclidean => dx * dx + dy * dy,
+            Metric::Weighted { wx, wy } => wx as u64 * dx + wy as u64 * dy,
+        }
+    }
+
+    /// The true distance; exact for everything but Euclidean.
+    pub fn distance(&self, ax: i32, ay: i32, bx: i32, by: i32) -> f64 {
+        match self {
+            Metric::Euclidean => (self.key(ax, ay, bx, by) as f64).sqrt(),
+            _ => self.key(ax, ay, bx, by) as f64,
+        }
+    }
+
+    /// The smallest distance between two cells that are a step apart along
+    /// either axis; every cell `d` steps outside a box is at least
+    /// `d * unit()` away from everything inside it.
+    pub fn unit(&self) -> u32 {
+        match *self {
+            Metric::Weighted { wx, wy } => wx.min(wy),
+            _ => 1,
+        }
+    }
+
+    /// The moves (dx, dy, cost) from a cell to its neighbours, such that the
+    /// cheapest path between two cells costs exactly their distance. This is
+    /// what allows regions to be flood-filled; there's no such set of moves
+    /// for Euclidean distance.
+    pub fn steps(&self) -> Option<Vec<(i32, i32, u64)>> {
+        let axes = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        match *self {
+            Metric::Manhattan => Some(axes.iter().map(|&(dx, dy)| (dx, dy, 1)).collect()),
+            Metric::Chebyshev => Some(
+                axes.iter()
+                    .chain(&[(-1, -1), (-1, 1), (1, -1), (1, 1)])
+                    .map(|&(dx, dy)| (dx, dy, 1))
+                    .collect()
+            ),
+            Metric::Euclidean => None,
+            Metric::Weighted { wx, wy } => Some(
+                axes.iter()
+                    .map(|&(dx, dy)| (dx, dy, if dx != 0 { wx as u64 } else { wy as u64 }))
+                    .collect()
+            ),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some(("weighted", weights)) => {
+                let (wx, wy) = weights
+                    .split_once(',')
+                    .ok_or("Weighted metric should look like: weighted:WX,WY")?;
+                let (wx, wy) = (wx.trim().parse()?, wy.trim().parse()?);
+                if wx == 0 || wy == 0 {
+                    return Err("Metric weights should be greater than zero".into());
+                }
+                Ok(Metric::Weighted { wx, wy })
+            },
+            _ => match s {
+                "manhattan" => Ok(Metric::Manhattan),
+                "chebyshev" => Ok(Metric::Chebyshev),
+                "euclidean" => Ok(Metric::Euclidean),
+                _ => Err(format!("Unknown metric (expected manhattan, chebyshev, euclidean or weighted:WX,WY): {}", s).into()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Metric::Manhattan => write!(f, "manhattan"),
+            Metric::Chebyshev => write!(f, "chebyshev"),
+            Metric::Euclidean => write!(f, "euclidean"),
+            Metric::Weighted { wx, wy } => write!(f, "weighted:{},{}", wx, wy),
+        }
+    }
+}
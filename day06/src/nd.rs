@@ -0,0 +1,241 @@
+use std::collections::VecDeque;
+
+use crate::Result;
+
+// owners of a cell that aren't a single point
+const UNREACHED: u32 = u32::MAX;
+const TIE: u32 = u32::MAX - 1;
+
+/// Points with any number of coordinates, under Manhattan distance.
+#[derive(Debug)]
+pub struct Space {
+    points: Vec<Vec<i32>>,
+    min: Vec<i32>,
+    max: Vec<i32>,
+}
+
+impl Space {
+    pub fn new(points: Vec<Vec<i32>>) -> Result<Self> {
+        let dims = points.first().map(|x| x.len()).ok_or("Could not find any valid coordinates in the input")?;
+        if dims == 0 || points.iter().any(|x| x.len() != dims) {
+            return Err("Every point should have the same (non-zero) number of coordinates".into());
+        }
+
+        let min = (0..dims).map(|d| points.iter().map(|p| p[d]).min().unwrap()).collect();
+        let max = (0..dims).map(|d| points.iter().map(|p| p[d]).max().unwrap()).collect();
+
+        Ok(Space { points, min, max })
+    }
+
+    pub fn dims(&self) -> usize {
+        self.min.len()
+    }
+
+    /// The size of the largest finite region (as in part 1).
+    ///
+    /// This floods outwards from every point at once over the bounding box
+    /// plus a one-cell shell, the same way `flood::flood_fill` does in 2-D.
+    /// A cell past the bounding box along one axis is owned by whoever owns
+    /// it once that coordinate is clamped to the shell (the distance along
+    /// that axis is the same for every point), so a point is infinite exactly
+    /// when it owns part of the shell.
+    pub fn largest_region(&self) -> Result<u64> {
+        let lo = self.min.iter().map(|x| x - 1).collect::<Vec<_>>();
+        let sizes = self.min
+            .iter()
+            .zip(&self.max)
+            .map(|(min, max)| (max - min + 3) as usize)
+            .collect::<Vec<_>>();
+        let mut strides = Vec::with_capacity(sizes.len());
+        let mut cells = 1usize;
+        for &size in &sizes {
+            strides.push(cells);
+            cells = cells
+                .checked_mul(size)
+                .filter(|&x| x <= u32::MAX as usize)
+                .ok_or("Bounding box of the points is too large to search")?;
+        }
+
+        let index = |coords: &[i32]| -> usize {
+            coords.iter().zip(&lo).zip(&strides).map(|((c, l), s)| (c - l) as usize * s).sum()
+        };
+
+        let mut owners = vec![UNREACHED; cells];
+        let mut dist = vec![u32::MAX; cells];
+        let mut queue = VecDeque::new();
+        for (id, point) in self.points.iter().enumerate() {
+            let idx = index(point);
+            if dist[idx] == u32::MAX {
+                dist[idx] = 0;
+                owners[idx] = id as u32;
+                queue.push_back(idx);
+            }
+            else {
+                owners[idx] = TIE;
+            }
+        }
+
+        // note: every step costs the same, so a plain breadth-first search
+        // settles the cells in order of distance; every cell has been reached
+        // from all of its closest neighbours before it's expanded
+        let mut coords = vec![0; sizes.len()];
+        while let Some(idx) = queue.pop_front() {
+            let mut rest = idx;
+            for d in (0..sizes.len()).rev() {
+                coords[d] = rest / strides[d];
+                rest %= strides[d];
+            }
+
+            for d in 0..sizes.len() {
+                let neighbours = [
+                    (coords[d] > 0).then(|| idx - strides[d]),
+                    (coords[d] + 1 < sizes[d]).then(|| idx + strides[d]),
+                ];
+                for nidx in neighbours.into_iter().flatten() {
+                    if dist[nidx] == u32::MAX {
+                        dist[nidx] = dist[idx] + 1;
+                        owners[nidx] = owners[idx];
+                        queue.push_back(nidx);
+                    }
+                    else if dist[nidx] == dist[idx] + 1 && owners[nidx] != owners[idx] {
+                        owners[nidx] = TIE;
+                    }
+                }
+            }
+        }
+
+        let mut areas = vec![0u64; self.points.len()];
+        let mut infinite = vec![false; self.points.len()];
+        for (idx, &owner) in owners.iter().enumerate() {
+            if owner == TIE || owner == UNREACHED {
+                continue
+            }
+
+            let shell = (0..sizes.len()).any(|d| {
+                let c = idx / strides[d] % sizes[d];
+                c == 0 || c + 1 == sizes[d]
+            });
+            infinite[owner as usize] |= shell;
+            areas[owner as usize] += 1;
+        }
+
+        Ok(areas
+            .into_iter()
+            .zip(infinite)
+            .filter(|x| !x.1)
+            .map(|x| x.0)
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// The number of cells whose total distance to every point is below
+    /// `threshold` (as in part 2).
+    ///
+    /// Manhattan distance adds up separately along each axis, so the total
+    /// distance of a cell is a sum of one (precomputed) total per coordinate;
+    /// the cells are counted one axis at a time, stopping as soon as a prefix
+    /// is over the threshold.
+    pub fn safe_area(&self, threshold: u32) -> Result<u64> {
+        // same margin as in 2-D: a cell `d` steps outside of the bounding box
+        // is at least `n * d` from the points in total
+        let margin = (threshold as u64).div_ceil(self.points.len() as u64);
+        let too_large = || format!("Threshold is too large: {}", threshold);
+        let margin = i32::try_from(margin).map_err(|_| too_large())?;
+        let ranges = (0..self.dims())
+            .map(|d| Some(self.min[d].checked_sub(margin)?..=self.max[d].checked_add(margin)?))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(too_large)?;
+
+        let totals = (0..self.dims())
+            .zip(ranges)
+            .map(|(d, range)| {
+                let mut totals = range
+                    .map(|v| self.points.iter().map(|p| (v - p[d]).unsigned_abs() as u64).sum())
+                    .collect::<Vec<u64>>();
+                // only the totals matter (not which coordinate they're for),
+                // so sort them to be able to stop early
+                totals.sort_unstable();
+                totals
+            })
+            .collect::<Vec<_>>();
+
+        Ok(count_below(&totals, 0, threshold as u64))
+    }
+}
+
+// the number of ways to pick one total per axis, with a sum below `limit`;
+// each axis' totals are sorted
+fn count_below(totals: &[Vec<u64>], partial: u64, limit: u64) -> u64 {
+    match totals {
+        [] => 1,
+        [axis] => axis.partition_point(|&x| partial + x < limit) as u64,
+        [axis, rest @ ..] => axis
+            .iter()
+            .take_while(|&&x| partial + x < limit)
+            .map(|&x| count_below(rest, partial + x, limit))
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::Metric;
+    use crate::{part1, part2, parse_coords, regions, DuplicatePolicy, Grid};
+    use std::fs;
+    use std::io;
+
+    fn load(path: &str) -> Vec<Vec<i32>> {
+        let buf = io::BufReader::new(fs::File::open(path).unwrap());
+        parse_coords(buf, DuplicatePolicy::Error).unwrap()
+    }
+
+    #[test]
+    fn line() {
+        // regions are split halfway between neighbours: 10 owns 7..=11,
+        // 14 owns 13..=19, and 25 owns 20..=32
+        let space = Space::new(load("./input/nd/line.txt")).unwrap();
+        assert_eq!(space.dims(), 1);
+        assert_eq!(space.largest_region().unwrap(), 13);
+        // the total distance is 52 at 14, and goes up by 1 per step until 10
+        // on one side (then by 3) and 25 on the other: 9..=21 are under 60
+        assert_eq!(space.safe_area(60).unwrap(), 13);
+        assert_eq!(space.safe_area(100).unwrap(), 39);
+    }
+
+    #[test]
+    fn cube() {
+        let space = Space::new(load("./input/nd/cube.txt")).unwrap();
+        assert_eq!(space.dims(), 3);
+        assert_eq!(space.largest_region().unwrap(), 1372);
+        assert_eq!(space.safe_area(2500).unwrap(), 0);
+        assert_eq!(space.safe_area(2600).unwrap(), 1597);
+    }
+
+    #[test]
+    fn tesseract() {
+        let space = Space::new(load("./input/nd/tesseract.txt")).unwrap();
+        assert_eq!(space.dims(), 4);
+        assert_eq!(space.largest_region().unwrap(), 145);
+        assert_eq!(space.safe_area(2800).unwrap(), 902);
+    }
+
+    #[test]
+    fn same_answers_as_2d() {
+        let inputs = [
+            ("./input/coordinates.txt", 10_000),
+            ("./input/spill/example.txt", 32),
+            ("./input/spill/single.txt", 10),
+            ("./input/spill/pair.txt", 32),
+            ("./input/spill/diamond.txt", 32),
+        ];
+        for (path, threshold) in inputs {
+            let space = Space::new(load(path)).unwrap();
+            let grid = Grid::from_coords(load(path)).unwrap();
+            let p1 = part1(&regions::regions(&grid, Metric::Manhattan)).unwrap();
+            let p2 = part2(&grid, Metric::Manhattan, threshold).unwrap();
+            assert_eq!(space.largest_region().unwrap(), p1, "{}", path);
+            assert_eq!(space.safe_area(threshold).unwrap(), p2, "{}", path);
+        }
+    }
+}
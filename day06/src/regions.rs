@@ -0,0 +1,382 @@
+use std::cmp;
+use std::io::Write;
+
+use crate::flood::{self, Owner};
+use crate::metric::Metric;
+use crate::{Grid, Point, PointId, Result};
+
+/// The cells closest to a single point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub id: PointId,
+    /// The number of cells owned by the point, or `None` if there are
+    /// infinitely many
+    pub area: Option<u64>,
+}
+
+/// Find the region of every point, in the same order as `grid.points`.
+///
+/// Note: this doesn't use a `KdTree`, since flooding the grid (or, for
+/// Euclidean distance, working out each region's rows directly) is cheaper
+/// than a tree search for every cell.
+pub fn regions(grid: &Grid, metric: Metric) -> Vec<Region> {
+    let infinite = unbounded(&grid.points, metric);
+    let areas = match metric {
+        Metric::Euclidean => grid.points
+            .iter()
+            .zip(&infinite)
+            .map(|(p, &inf)| if inf { 0 } else { euclidean_area(&grid.points, p) })
+            .collect(),
+        _ => flood_areas(grid, metric, &infinite),
+    };
+
+    grid.points
+        .iter()
+        .zip(infinite)
+        .zip(areas)
+        .map(|((p, inf), area)| Region { id: p.id, area: if inf { None } else { Some(area) } })
+        .collect()
+}
+
+/// Which points own infinitely many cells, worked out from the positions of
+/// the points alone.
+///
+/// Far enough away from the points, every cell in a given direction (and
+/// offset) is owned by whichever point is furthest along that direction, so
+/// a point is unbounded exactly when it's the *only* point that's furthest
+/// along some direction. Two points at the same position are never
+/// unbounded, since they tie everywhere.
+pub fn unbounded(points: &[Point], metric: Metric) -> Vec<bool> {
+    match metric {
+        Metric::Manhattan => envelopes(points, 1, 1),
+        Metric::Weighted { wx, wy } => envelopes(points, wx as i64, wy as i64),
+        Metric::Chebyshev => diagonals(points),
+        Metric::Euclidean => hull(points),
+    }
+}
+
+// mark `points[i]` if it's the only point with the highest score
+fn mark_unique_max<F: Fn(&Point) -> i64>(points: &[Point], marked: &mut [bool], score: F) {
+    let mut best = (i64::MIN, None);
+    for (i, p) in points.iter().enumerate() {
+        let s = score(p);
+        if s > best.0 {
+            best = (s, Some(i));
+        }
+        else if s == best.0 {
+            best.1 = None;
+        }
+    }
+
+    if let Some(i) = best.1 {
+        marked[i] = true;
+    }
+}
+
+fn extent<F: Fn(&Point) -> i64>(points: &[Point], f: F) -> (i64, i64) {
+    let min = points.iter().map(&f).min().unwrap_or(0);
+    let max = points.iter().map(&f).max().unwrap_or(0);
+    (min, max)
+}
+
+// for (weighted) Manhattan distance, a cell at (X, y) to the right of every
+// point is `wx * X - (wx * px - wy * |y - py|)` away from each point, so its
+// owner doesn't depend on X; once y is past the points, the owner doesn't
+// depend on y either. The same goes for the other three sides
+fn envelopes(points: &[Point], wx: i64, wy: i64) -> Vec<bool> {
+    let mut marked = vec![false; points.len()];
+    let (xmin, xmax) = extent(points, |p| p.x as i64);
+    let (ymin, ymax) = extent(points, |p| p.y as i64);
+
+    for sign in [-1, 1] {
+        for y in (ymin - 1)..=(ymax + 1) {
+            mark_unique_max(points, &mut marked, |p| sign * wx * p.x as i64 - wy * (y - p.y as i64).abs());
+        }
+        for x in (xmin - 1)..=(xmax + 1) {
+            mark_unique_max(points, &mut marked, |p| sign * wy * p.y as i64 - wx * (x - p.x as i64).abs());
+        }
+    }
+
+    marked
+}
+
+// for Chebyshev distance, a far away cell at (X, X - c) (up and to the
+// right, along a diagonal) is `X - min(px, py + c)` away from each point.
+// Past the ends of the range of offsets, this is just the point with the
+// largest x (or y), so each diagonal covers the sides next to it too
+fn diagonals(points: &[Point]) -> Vec<bool> {
+    let mut marked = vec![false; points.len()];
+    for sx in [-1, 1] {
+        for sy in [-1, 1] {
+            let (min, max) = extent(points, |p| sx * p.x as i64 - sy * p.y as i64);
+            for c in (min - 1)..=(max + 1) {
+                mark_unique_max(points, &mut marked, |p| cmp::min(sx * p.x as i64, sy * p.y as i64 + c));
+            }
+        }
+    }
+
+    marked
+}
+
+// for Euclidean distance, the unbounded points are the ones on the boundary
+// of the convex hull, including any that lie along one of its edges
+fn hull(points: &[Point]) -> Vec<bool> {
+    let mut sorted = points
+        .iter()
+        .map(|p| (p.x as i64, p.y as i64))
+        .collect::<Vec<_>>();
+    sorted.sort();
+    sorted.dedup();
+
+    // Andrew's monotone chain, keeping only the corners
+    let cross = |o: (i64, i64), a: (i64, i64), b: (i64, i64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut corners: Vec<(i64, i64)> = Vec::new();
+    for pass in [sorted.clone(), sorted.iter().rev().copied().collect()] {
+        let start = corners.len();
+        for p in pass {
+            while corners.len() >= start + 2 && cross(corners[corners.len() - 2], corners[corners.len() - 1], p) <= 0 {
+                corners.pop();
+            }
+            corners.push(p);
+        }
+        corners.pop();
+    }
+    if corners.is_empty() {
+        // a single (distinct) point
+        corners.extend(sorted.first());
+    }
+
+    let on_hull = |p: (i64, i64)| {
+        (0..corners.len()).any(|i| {
+            let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+            cross(a, b, p) == 0
+                && p.0 >= a.0.min(b.0) && p.0 <= a.0.max(b.0)
+                && p.1 >= a.1.min(b.1) && p.1 <= a.1.max(b.1)
+        })
+    };
+
+    points
+        .iter()
+        .map(|p| {
+            let duplicated = points.iter().filter(|q| (q.x, q.y) == (p.x, p.y)).count() > 1;
+            !duplicated && on_hull((p.x as i64, p.y as i64))
+        })
+        .collect()
+}
+
+// a bounded region can still spill past the bounding box (e.g. for
+// Chebyshev distance), so keep growing the area that's filled until none of
+// its border belongs to a bounded region. Cells are only owned when the
+// neighbour they're reached from has the same owner, so each region is
+// connected and can't reappear further out
+fn flood_areas(grid: &Grid, metric: Metric, infinite: &[bool]) -> Vec<u64> {
+    let mut margin = 1;
+    loop {
+        let owners = flood::flood_fill(
+            &grid.points,
+            metric,
+            grid.xmin - margin, grid.xmax + margin,
+            grid.ymin - margin, grid.ymax + margin,
+        );
+
+        let mut areas = vec![0; grid.points.len()];
+        let mut spilled = false;
+        for (x, y, owner) in owners.iter() {
+            let Owner::Point(id) = owner else { continue };
+            if infinite[id] {
+                continue
+            }
+
+            areas[id] += 1;
+            let border = x == owners.xmin || y == owners.ymin
+                || x == owners.xmin + owners.w - 1 || y == owners.ymin + owners.h - 1;
+            spilled |= border;
+        }
+
+        if !spilled {
+            return areas;
+        }
+        margin *= 2;
+    }
+}
+
+// every cell closer to `p` than to another point `q` satisfies
+// `2 * (x * (qx - px) + y * (qy - py)) < |q|^2 - |p|^2`, so each row of a
+// bounded region is a single run of cells that can be counted directly. The
+// region is convex, so the rows stop as soon as one is empty (even between
+// cells)
+fn euclidean_area(points: &[Point], p: &Point) -> u64 {
+    let mut area = 0;
+    for dir in [1, -1] {
+        let mut y = p.y as i64 + if dir == 1 { 0 } else { -1 };
+        while let Some((lo, hi)) = euclidean_row(points, p, y) {
+            area += cmp::max(hi - lo + 1, 0) as u64;
+            y += dir;
+        }
+    }
+
+    area
+}
+
+// the cells of row `y` closer to `p` than any other point, as an inclusive
+// range (which may be empty), or `None` if the region doesn't reach this
+// row at all
+fn euclidean_row(points: &[Point], p: &Point, y: i64) -> Option<(i64, i64)> {
+    let (px, py) = (p.x as i64, p.y as i64);
+    // the bounds as fractions (numerator, positive denominator), along with
+    // the whole cells just inside of them
+    let mut lower: Option<(i64, i64)> = None;
+    let mut upper: Option<(i64, i64)> = None;
+    let (mut lo, mut hi) = (i64::MIN, i64::MAX);
+
+    for q in points.iter().filter(|q| q.id != p.id) {
+        let (qx, qy) = (q.x as i64, q.y as i64);
+        let a = 2 * (qx - px);
+        let b = (qx * qx + qy * qy) - (px * px + py * py) - 2 * y * (qy - py);
+        match a.signum() {
+            0 if b <= 0 => return None,
+            0 => {},
+            1 => {
+                hi = hi.min((b - 1).div_euclid(a));
+                if upper.is_none_or(|(n, d)| b * d < n * a) {
+                    upper = Some((b, a));
+                }
+            },
+            _ => {
+                lo = lo.max((-b).div_euclid(-a) + 1);
+                if lower.is_none_or(|(n, d)| -b * d > n * -a) {
+                    lower = Some((-b, -a));
+                }
+            },
+        }
+    }
+
+    match (lower, upper) {
+        (Some((ln, ld)), Some((un, ud))) if ln * ud >= un * ld => None,
+        _ => Some((lo, hi)),
+    }
+}
+
+/// Write out the area (or "infinite") of every point's region.
+pub fn write_report<W: Write>(mut out: W, grid: &Grid, regions: &[Region]) -> Result<()> {
+    writeln!(out, "Point      X      Y  Area")?;
+    for (point, region) in grid.points.iter().zip(regions) {
+        let area = match region.area {
+            Some(area) => area.to_string(),
+            None => "infinite".to_string(),
+        };
+        writeln!(out, "{:<5}  {:>5}  {:>5}  {}", point.id, point.x, point.y, area)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random_points;
+
+    const METRICS: [Metric; 5] = [
+        Metric::Manhattan,
+        Metric::Chebyshev,
+        Metric::Euclidean,
+        Metric::Weighted { wx: 3, wy: 1 },
+        Metric::Weighted { wx: 1, wy: 4 },
+    ];
+
+    fn grid(coords: &[(i32, i32)]) -> Grid {
+        Grid::from_coords(coords.iter().map(|&(x, y)| vec![x, y]).collect()).unwrap()
+    }
+
+    // check every cell of a box far past the points; an unbounded region
+    // always reaches the ring between `inner` and `outer` (which is wider
+    // than the gap between the cells of a Euclidean strip). Bounded regions
+    // can reach quite far too (a Euclidean one next to an almost flat edge of
+    // the hull is long and thin), but not this far for any of these inputs
+    fn assert_matches_brute_force(grid: &Grid, metric: Metric) {
+        let size = cmp::max(grid.w, grid.h);
+        let (inner, outer) = (12 * size, 14 * size);
+        let mut areas = vec![0; grid.points.len()];
+        let mut infinite = vec![false; grid.points.len()];
+        for y in (grid.ymin - outer)..=(grid.ymax + outer) {
+            for x in (grid.xmin - outer)..=(grid.xmax + outer) {
+                let Owner::Point(id) = flood::closest_owner(&grid.points, metric, x, y) else { continue };
+                areas[id] += 1;
+                infinite[id] |= x < grid.xmin - inner || x > grid.xmax + inner
+                    || y < grid.ymin - inner || y > grid.ymax + inner;
+            }
+        }
+
+        let expected = grid.points
+            .iter()
+            .map(|p| Region { id: p.id, area: (!infinite[p.id]).then_some(areas[p.id]) })
+            .collect::<Vec<_>>();
+        let points = grid.points.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+        assert_eq!(regions(grid, metric), expected, "{} with {:?}", metric, points);
+    }
+
+    #[test]
+    fn random_points_for_every_metric() {
+        for seed in 1..=10 {
+            let grid = Grid::new(random_points(12, 16, seed)).unwrap();
+            for metric in METRICS {
+                assert_matches_brute_force(&grid, metric);
+            }
+        }
+    }
+
+    #[test]
+    fn the_puzzle_example() {
+        let grid = grid(&[(1, 1), (1, 6), (8, 3), (3, 4), (5, 5), (8, 9)]);
+        for metric in METRICS {
+            assert_matches_brute_force(&grid, metric);
+        }
+    }
+
+    #[test]
+    fn collinear_hull_edges() {
+        let cases: [&[(i32, i32)]; 4] = [
+            // points along the bottom edge of the hull
+            &[(0, 0), (3, 0), (6, 0), (9, 0), (0, 6), (9, 6), (4, 3)],
+            // and along a slanted edge
+            &[(0, 0), (2, 1), (4, 2), (6, 3), (0, 6), (3, 5)],
+            // everything on one line
+            &[(0, 0), (1, 1), (2, 2), (5, 5)],
+            &[(0, 0), (0, 3), (0, 7)],
+        ];
+        for points in cases {
+            for metric in METRICS {
+                assert_matches_brute_force(&grid(points), metric);
+            }
+        }
+    }
+
+    #[test]
+    fn chebyshev_diagonals() {
+        let cases: [&[(i32, i32)]; 5] = [
+            &[(0, 0), (4, 4), (8, 8), (0, 8), (8, 0)],
+            &[(0, 0), (3, 3), (6, 6)],
+            &[(0, 0), (4, 4), (4, 0), (0, 4), (2, 2)],
+            &[(0, 0), (2, 0), (5, 3), (0, 5)],
+            &[(0, 0), (6, 2), (2, 6), (7, 7), (3, 3)],
+        ];
+        for points in cases {
+            for metric in METRICS {
+                assert_matches_brute_force(&grid(points), metric);
+            }
+        }
+    }
+
+    #[test]
+    fn duplicated_points() {
+        let points = [(0, 0), (0, 0), (5, 5), (5, 0)];
+        let coords = points.iter().map(|&(x, y)| vec![x, y]).collect::<Vec<_>>();
+        // `from_coords` doesn't check for duplicates (that's up to the parser)
+        let grid = Grid::from_coords(coords).unwrap();
+        for metric in METRICS {
+            assert_matches_brute_force(&grid, metric);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::io::Write;
+
+use crate::flood::{self, Owner};
+use crate::metric::Metric;
+use crate::regions::Region;
+use crate::{Grid, Result, SafeRegion};
+
+type Rgb = [u8; 3];
+
+const COLOUR_TIE: Rgb = [96, 96, 96];
+const COLOUR_POINT: Rgb = [0, 0, 0];
+const COLOUR_SAFE: Rgb = [255, 255, 255];
+
+/// Write the regions owned by each point out as a binary PPM (P6) image, one
+/// pixel per cell.
+///
+/// Each point's region gets its own colour, with the infinite regions drawn
+/// dimmed and tied cells drawn grey. The points themselves are marked in
+/// black, and the safe region is outlined in white. The image covers the
+/// bounding box (plus a one-cell border), grown to fit the safe region.
+pub fn write_regions<W: Write>(
+    mut out: W,
+    grid: &Grid,
+    metric: Metric,
+    regions: &[Region],
+    safe: &SafeRegion,
+) -> Result<()> {
+    let xmin = (grid.xmin - 1).min(safe.xmin);
+    let ymin = (grid.ymin - 1).min(safe.ymin);
+    let xmax = (grid.xmax + 1).max(safe.xmin + safe.w - 1);
+    let ymax = (grid.ymax + 1).max(safe.ymin + safe.h - 1);
+    let owners = flood::flood_fill(&grid.points, metric, xmin, xmax, ymin, ymax);
+    let (w, h) = (owners.w, owners.h);
+
+    let colours = regions
+        .iter()
+        .map(|x| match x.area {
+            Some(_) => region_colour(x.id),
+            None => region_colour(x.id).map(|x| x / 3),
+        })
+        .collect::<Vec<_>>();
+
+    let mut pixels = owners
+        .iter()
+        .map(|(_, _, owner)| match owner {
+            Owner::Point(id) => colours[id],
+            Owner::Tie | Owner::Unreached => COLOUR_TIE,
+        })
+        .collect::<Vec<_>>();
+
+    // a safe cell is on the outline if any of its neighbours isn't safe
+    for y in ymin..=ymax {
+        for x in xmin..=xmax {
+            let edge = safe.contains(x, y)
+                && [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    .iter()
+                    .any(|&(dx, dy)| !safe.contains(x + dx, y + dy));
+            if edge {
+                pixels[((x - xmin) + w * (y - ymin)) as usize] = COLOUR_SAFE;
+            }
+        }
+    }
+
+    // mark the points last, as a small cross so they stand out at one pixel
+    // per cell
+    for point in &grid.points {
+        for (dx, dy) in [(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let (x, y) = (point.x + dx - xmin, point.y + dy - ymin);
+            if x >= 0 && y >= 0 && x < w && y < h {
+                pixels[(x + w * y) as usize] = COLOUR_POINT;
+            }
+        }
+    }
+
+    write!(out, "P6\n{} {}\n255\n", w, h)?;
+    for pixel in pixels {
+        out.write_all(&pixel)?;
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+// spread the hues out using the golden angle, so neighbouring ids (which
+// are often neighbouring regions) never end up with similar colours
+fn region_colour(id: usize) -> Rgb {
+    let hue = (id as f64 * 137.508) % 360.0;
+    hsv_to_rgb(hue, 0.65, 0.95)
+}
+
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Rgb {
+    let c = value * saturation;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = value - c;
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    [r, g, b].map(|x| ((x + m) * 255.0).round() as u8)
+}